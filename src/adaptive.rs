@@ -0,0 +1,51 @@
+// Keeps taking samples in noisy pixels after the first `spp`, in rounds of `spp`,
+// until their estimated error drops below `threshold` or they reach `max_spp`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Adaptive {
+    // Standard error of a pixel's mean luminance, relative to that mean
+    pub threshold: f64,
+    pub max_spp: u32,
+}
+
+// Luminance below this counts as this bright when judging error, so dark pixels
+// don't need to be sampled until their error is close to zero
+const MIN_LUMINANCE: f64 = 0.05;
+
+// Running mean and variance of a pixel's samples, using Welford's algorithm
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct PixelStats {
+    pub(crate) count: u32,
+    pub(crate) mean: f64,
+    // Sum of squared differences from the mean
+    pub(crate) m2: f64,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    // Unbiased estimate of the variance of a single sample
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+
+    // Estimated error of the mean relative to the mean
+    pub fn relative_error(&self) -> f64 {
+        (self.variance() / self.count as f64).sqrt() / self.mean.abs().max(MIN_LUMINANCE)
+    }
+}
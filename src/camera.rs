@@ -1,7 +1,8 @@
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::vec3::Vector3;
 
-use rand::Rng;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
 
 #[allow(dead_code)]
 pub struct Camera {
@@ -52,8 +53,15 @@ impl Camera {
         }
     }
 
-    pub fn ray(&self, s: f64, t: f64) -> Ray {
-        let rd: Vector3 = random_in_unit_disc() * self.lens_radius;
+    // Keeps the vertical field of view and focus plane, stretching the viewport horizontally
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        let center = self.ll_corner + (self.horiz / 2.0) + (self.vert / 2.0);
+        self.horiz = self.u * self.vert.length() * aspect_ratio;
+        self.ll_corner = center - (self.horiz / 2.0) - (self.vert / 2.0);
+    }
+
+    pub fn ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd: Vector3 = random_in_unit_disc(sampler) * self.lens_radius;
         let offset: Vector3 = self.u * rd.x + self.v * rd.y;
 
         Ray::new(
@@ -63,14 +71,17 @@ impl Camera {
     }
 }
 
-pub fn random_in_unit_disc() -> Vector3 {
-    let mut rng = rand::thread_rng();
-    loop {
-        let p = Vector3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), 0.0);
-
-        if p.dot(&p) >= 1.0 {
-            continue;
-        }
-        return p;
+// Concentric mapping from the square, which keeps stratified samples evenly spread
+pub fn random_in_unit_disc(sampler: &mut dyn Sampler) -> Vector3 {
+    let (s, t) = sampler.get_2d();
+    let (a, b) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vector3::new(0.0, 0.0, 0.0);
     }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    Vector3::new(r * theta.cos(), r * theta.sin(), 0.0)
 }
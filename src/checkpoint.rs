@@ -0,0 +1,358 @@
+use crate::adaptive::PixelStats;
+use crate::film::{Film, FilmPixel};
+use crate::filters::Filter;
+
+use rgb::RGB;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"RTCKPT01";
+const MAX_HEADER_LEN: usize = 1 << 16;
+// Color and weight, then the sample count
+const PIXEL_BYTES: u64 = 4 * 8 + 4;
+// Sample count, mean and M2
+const STATS_BYTES: u64 = 4 + 8 + 8;
+
+// Everything that changes the result of a render apart from the scene itself.
+// A render can only be resumed with the same settings.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    pub spp: u32,
+    pub max_spp: u32,
+    pub adaptive_threshold: Option<f64>,
+    pub pass_spp: u32,
+    pub filter: String,
+    pub filter_radius: f64,
+    pub sampler: String,
+    pub integrator: String,
+    pub max_depth: u32,
+    pub rr_depth: u32,
+}
+
+// State of a render between passes. Saved as a binary file with the settings in a
+// TOML header, followed by the seed, the pixels and their sample statistics.
+pub struct Checkpoint {
+    pub settings: Settings,
+    // Every sample's random numbers are derived from this, so it's all the
+    // random state there is
+    pub seed: u64,
+    // Every pixel has this many samples, unless adaptive sampling stopped it early
+    pub spp_done: u32,
+    pub film: Film,
+    // One per pixel, in the same order as the film
+    pub stats: Vec<PixelStats>,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    // Not a checkpoint, or a damaged one
+    Format(String),
+    // The render being resumed doesn't match the checkpoint
+    Mismatch {
+        setting: String,
+        checkpoint: String,
+        render: String,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "{}", e),
+            CheckpointError::Format(message) => write!(f, "invalid checkpoint: {}", message),
+            CheckpointError::Mismatch {
+                setting,
+                checkpoint,
+                render,
+            } => write!(
+                f,
+                "{} is {} in the checkpoint but {} in this render",
+                setting, checkpoint, render
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(e: std::io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+impl Settings {
+    pub fn filter(&self) -> Option<Filter> {
+        Filter::from_name(&self.filter, Some(self.filter_radius))
+    }
+
+    // Fails on the first setting that differs
+    pub fn check(&self, render: &Settings) -> Result<(), CheckpointError> {
+        let (a, b) = (toml_table(self), toml_table(render));
+        for (key, value) in &a {
+            let other = b.get(key);
+            if other != Some(value) {
+                return Err(CheckpointError::Mismatch {
+                    setting: key.clone(),
+                    checkpoint: value.to_string(),
+                    render: other.map_or_else(|| "not set".to_string(), |v| v.to_string()),
+                });
+            }
+        }
+        if let Some((key, value)) = b.iter().find(|(key, _)| !a.contains_key(*key)) {
+            return Err(CheckpointError::Mismatch {
+                setting: key.clone(),
+                checkpoint: "not set".to_string(),
+                render: value.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn toml_table(settings: &Settings) -> toml::value::Table {
+    match toml::Value::try_from(settings) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => unreachable!("settings always serialize to a table"),
+    }
+}
+
+impl Checkpoint {
+    // Nothing rendered yet
+    pub fn new(settings: Settings, seed: u64, filter: Filter) -> Checkpoint {
+        let pixels = (settings.width * settings.height) as usize;
+        Checkpoint {
+            film: Film::new(settings.width, settings.height, filter),
+            stats: vec![PixelStats::default(); pixels],
+            settings,
+            seed,
+            spp_done: 0,
+        }
+    }
+
+    // Writes to a temporary file first, so the previous checkpoint survives
+    // being interrupted
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        let header =
+            toml::to_string(&self.settings).map_err(|e| CheckpointError::Format(e.to_string()))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(header.len() as u64).to_le_bytes())?;
+        file.write_all(header.as_bytes())?;
+        file.write_all(&self.seed.to_le_bytes())?;
+        file.write_all(&self.spp_done.to_le_bytes())?;
+        for px in &self.film.pixels {
+            for v in &[px.color.r, px.color.g, px.color.b, px.weight] {
+                file.write_all(&v.to_le_bytes())?;
+            }
+            file.write_all(&px.samples.to_le_bytes())?;
+        }
+        for stats in &self.stats {
+            file.write_all(&stats.count.to_le_bytes())?;
+            file.write_all(&stats.mean.to_le_bytes())?;
+            file.write_all(&stats.m2.to_le_bytes())?;
+        }
+        file.flush()?;
+        drop(file);
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Checkpoint, CheckpointError> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut file = BufReader::new(file);
+        let magic: [u8; 8] = read_bytes(&mut file)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format("not a checkpoint file".to_string()));
+        }
+        let header_len = read_u64(&mut file)? as usize;
+        if header_len > MAX_HEADER_LEN {
+            return Err(CheckpointError::Format("header is too long".to_string()));
+        }
+        let mut header = vec![0; header_len];
+        file.read_exact(&mut header)
+            .map_err(|_| CheckpointError::Format("file is truncated".to_string()))?;
+        let header = String::from_utf8(header)
+            .map_err(|_| CheckpointError::Format("header isn't UTF-8".to_string()))?;
+        let settings: Settings =
+            toml::from_str(&header).map_err(|e| CheckpointError::Format(e.to_string()))?;
+        let filter = settings.filter().ok_or_else(|| {
+            CheckpointError::Format(format!("unknown filter {}", settings.filter))
+        })?;
+
+        // The header can't be trusted, so check the size it gives against the file
+        // before allocating anything
+        let data_len = settings
+            .width
+            .checked_mul(settings.height)
+            .and_then(|pixels| (pixels as u64).checked_mul(PIXEL_BYTES + STATS_BYTES))
+            .and_then(|len| len.checked_add(8 + 4));
+        let remaining = file_len.checked_sub((MAGIC.len() + 8 + header_len) as u64);
+        if data_len.is_none() || data_len != remaining {
+            return Err(CheckpointError::Format(format!(
+                "file size doesn't match a {} x {} image",
+                settings.width, settings.height
+            )));
+        }
+
+        let seed = read_u64(&mut file)?;
+        let mut checkpoint = Checkpoint::new(settings, seed, filter);
+        checkpoint.spp_done = read_u32(&mut file)?;
+        for px in &mut checkpoint.film.pixels {
+            let mut values = [0.; 4];
+            for v in &mut values {
+                *v = read_f64(&mut file)?;
+            }
+            *px = FilmPixel {
+                color: RGB::new(values[0], values[1], values[2]),
+                weight: values[3],
+                samples: read_u32(&mut file)?,
+            };
+        }
+        for stats in &mut checkpoint.stats {
+            stats.count = read_u32(&mut file)?;
+            stats.mean = read_f64(&mut file)?;
+            stats.m2 = read_f64(&mut file)?;
+        }
+        if file.read(&mut [0])? != 0 {
+            return Err(CheckpointError::Format(
+                "unexpected data at the end".to_string(),
+            ));
+        }
+        Ok(checkpoint)
+    }
+}
+
+fn read_bytes<const N: usize>(file: &mut impl Read) -> Result<[u8; N], CheckpointError> {
+    let mut bytes = [0; N];
+    file.read_exact(&mut bytes).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => CheckpointError::Format("file is truncated".to_string()),
+        _ => CheckpointError::Io(e),
+    })?;
+    Ok(bytes)
+}
+
+fn read_u32(file: &mut impl Read) -> Result<u32, CheckpointError> {
+    Ok(u32::from_le_bytes(read_bytes(file)?))
+}
+
+fn read_u64(file: &mut impl Read) -> Result<u64, CheckpointError> {
+    Ok(u64::from_le_bytes(read_bytes(file)?))
+}
+
+fn read_f64(file: &mut impl Read) -> Result<f64, CheckpointError> {
+    Ok(f64::from_bits(read_u64(file)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn settings() -> Settings {
+        Settings {
+            width: 3,
+            height: 2,
+            spp: 4,
+            max_spp: 4,
+            adaptive_threshold: None,
+            pass_spp: 2,
+            filter: "box".to_string(),
+            filter_radius: 0.5,
+            sampler: "sobol".to_string(),
+            integrator: "path".to_string(),
+            max_depth: 10,
+            rr_depth: 3,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let filter = Filter::Mitchell { radius: 2. };
+        let mut checkpoint = Checkpoint::new(settings(), u64::MAX - 5, filter);
+        checkpoint.spp_done = 2;
+        for (i, px) in checkpoint.film.pixels.iter_mut().enumerate() {
+            let x = i as f64;
+            px.color = RGB::new(x * 0.1, -x, 1. / (x + 3.));
+            px.weight = 0.5 + x;
+            px.samples = i as u32;
+        }
+        for (i, stats) in checkpoint.stats.iter_mut().enumerate() {
+            for j in 0..=i {
+                stats.add((i * j) as f64 / 7.);
+            }
+        }
+
+        let path = temp_path("round-trip.ckpt");
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.settings, checkpoint.settings);
+        assert_eq!(loaded.seed, checkpoint.seed);
+        assert_eq!(loaded.spp_done, checkpoint.spp_done);
+        assert_eq!(loaded.film.pixels, checkpoint.film.pixels);
+        assert_eq!(loaded.stats, checkpoint.stats);
+    }
+
+    #[test]
+    fn check_reports_the_setting_that_differs() {
+        let mut render = settings();
+        assert!(settings().check(&render).is_ok());
+
+        render.rr_depth = 5;
+        match settings().check(&render) {
+            Err(CheckpointError::Mismatch {
+                setting,
+                checkpoint,
+                render,
+            }) => {
+                assert_eq!(setting, "rr_depth");
+                assert_eq!(checkpoint, "3");
+                assert_eq!(render, "5");
+            }
+            _ => panic!("expected a mismatch"),
+        }
+
+        let mut render = settings();
+        render.adaptive_threshold = Some(0.1);
+        assert!(matches!(
+            settings().check(&render),
+            Err(CheckpointError::Mismatch { setting, .. }) if setting == "adaptive_threshold"
+        ));
+    }
+
+    #[test]
+    fn rejects_size_that_does_not_match_the_header() {
+        let path = temp_path("huge.ckpt");
+        let mut huge = settings();
+        huge.width = 1 << 20;
+        huge.height = 1 << 20;
+        let header = toml::to_string(&huge).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(&[0; 12]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = Checkpoint::load(&path);
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(result, Err(CheckpointError::Format(_))));
+    }
+}
@@ -0,0 +1,96 @@
+// Piecewise-constant distributions for sampling proportionally to tabulated values
+pub struct Distribution1D {
+    func: Vec<f64>,
+    // cdf[i] is the probability of landing before cell i; cdf[n] is 1
+    cdf: Vec<f64>,
+    // Integral of `func` over [0, 1]
+    integral: f64,
+}
+
+// Samples a grid of cells: a row from the marginal, then a column within that row
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution1D {
+    // Values must not be negative. All zero values are sampled uniformly.
+    pub fn new(func: Vec<f64>) -> Distribution1D {
+        assert!(!func.is_empty());
+        let n = func.len() as f64;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.);
+        for (i, f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f / n);
+        }
+
+        let integral = cdf[func.len()];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0. {
+                *c / integral
+            } else {
+                i as f64 / n
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Density over [0, 1] of any point in cell `i`
+    pub fn pdf(&self, i: usize) -> f64 {
+        if self.integral > 0. {
+            self.func[i] / self.integral
+        } else {
+            1.
+        }
+    }
+
+    // Maps `u` in [0, 1) to a point in [0, 1) and the cell it's in
+    pub fn sample(&self, u: f64) -> (f64, usize) {
+        // Last cell whose cdf starts at or before u, skipping empty cells
+        let i = match self.cdf.partition_point(|&c| c <= u) {
+            0 => 0,
+            i => (i - 1).min(self.func.len() - 1),
+        };
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0. {
+            ((u - self.cdf[i]) / width).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let x = (i as f64 + offset) / self.func.len() as f64;
+        (x.min(1. - f64::EPSILON), i)
+    }
+}
+
+impl Distribution2D {
+    // `values` holds `width` values per row
+    pub fn new(values: &[f64], width: usize) -> Distribution2D {
+        let rows: Vec<Distribution1D> = values
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    // Maps `u` in [0, 1)^2 to a point in [0, 1)^2 as (column, row) fractions,
+    // with its density
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (y, row) = self.marginal.sample(u.1);
+        let (x, col) = self.rows[row].sample(u.0);
+        ((x, y), self.pdf(col, row))
+    }
+
+    // Density over [0, 1]^2 of any point in the cell at `col`, `row`
+    pub fn pdf(&self, col: usize, row: usize) -> f64 {
+        self.marginal.pdf(row) * self.rows[row].pdf(col)
+    }
+}
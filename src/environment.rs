@@ -0,0 +1,81 @@
+pub mod map;
+pub mod sky;
+
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use rgb::RGB;
+use std::sync::Arc;
+
+// Light arriving from infinitely far away, seen by rays that leave the scene
+pub trait Environment: Sync + Send {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64>;
+
+    // Density, over solid angle, with which `sample_direction` picks `dir`.
+    // Environments that can't be sampled return 0 and are only found by chance.
+    fn pdf_value(&self, _dir: &Vector3) -> f64 {
+        0.
+    }
+
+    // Random direction towards the environment, used to sample it as a light
+    fn sample_direction(&self, _sampler: &mut dyn Sampler) -> Vector3 {
+        Vector3::new(1., 0., 0.)
+    }
+}
+
+impl<E: Environment + ?Sized> Environment for Arc<E> {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64> {
+        (**self).radiance(dir)
+    }
+
+    fn pdf_value(&self, dir: &Vector3) -> f64 {
+        (**self).pdf_value(dir)
+    }
+
+    fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vector3 {
+        (**self).sample_direction(sampler)
+    }
+}
+
+// Blends from `bottom` straight down to `top` straight up
+pub struct Gradient {
+    pub bottom: RGB<f64>,
+    pub top: RGB<f64>,
+}
+
+// Same radiance in every direction
+pub struct Constant {
+    pub color: RGB<f64>,
+}
+
+impl Gradient {
+    pub fn new(bottom: RGB<f64>, top: RGB<f64>) -> Gradient {
+        Gradient { bottom, top }
+    }
+}
+
+// White to blue sky
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient::new(RGB::new(1., 1., 1.), RGB::new(0.5, 0.75, 1.))
+    }
+}
+
+impl Environment for Gradient {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64> {
+        let t = 0.5 * (dir.unit_vec().y + 1.);
+        self.bottom * (1. - t) + self.top * t
+    }
+}
+
+impl Constant {
+    pub fn new(color: RGB<f64>) -> Constant {
+        Constant { color }
+    }
+}
+
+impl Environment for Constant {
+    fn radiance(&self, _dir: &Vector3) -> RGB<f64> {
+        self.color
+    }
+}
@@ -0,0 +1,271 @@
+use crate::distribution::Distribution2D;
+use crate::environment::Environment;
+use crate::objects::sphere::Sphere;
+use crate::output::HdrFormat;
+use crate::samplers::Sampler;
+use crate::tonemap;
+use crate::transform::Transform;
+use crate::vec3::Vector3;
+
+use image::hdr::HdrDecoder;
+use image::ImageError;
+use rgb::RGB;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// Equirectangular (lat-long) image around the scene, laid out like the texture
+// coordinates of `Sphere::get_uv`
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    // Linear radiance in rows from top to bottom
+    pixels: Vec<RGB<f64>>,
+    // Proportional to luminance times the solid angle each pixel covers
+    distribution: Distribution2D,
+    rotation: Transform,
+    intensity: f64,
+}
+
+#[derive(Debug)]
+pub enum EnvironmentError {
+    Io(std::io::Error),
+    Image(ImageError),
+    Exr(exr::error::Error),
+}
+
+impl fmt::Display for EnvironmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EnvironmentError::Io(e) => write!(f, "{}", e),
+            EnvironmentError::Image(e) => write!(f, "{}", e),
+            EnvironmentError::Exr(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for EnvironmentError {}
+
+impl From<std::io::Error> for EnvironmentError {
+    fn from(e: std::io::Error) -> Self {
+        EnvironmentError::Io(e)
+    }
+}
+
+impl From<ImageError> for EnvironmentError {
+    fn from(e: ImageError) -> Self {
+        EnvironmentError::Image(e)
+    }
+}
+
+impl From<exr::error::Error> for EnvironmentError {
+    fn from(e: exr::error::Error) -> Self {
+        EnvironmentError::Exr(e)
+    }
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<RGB<f64>>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height);
+        assert!(width > 0 && height > 0);
+        let weights: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, px)| tonemap::luminance(*px).max(0.) * sin_theta(i / width, height))
+            .collect();
+        EnvironmentMap {
+            width,
+            height,
+            distribution: Distribution2D::new(&weights, width),
+            pixels,
+            rotation: Transform::identity(),
+            intensity: 1.,
+        }
+    }
+
+    // .hdr and .exr files are read as linear radiance,
+    // other images are assumed to be sRGB encoded
+    pub fn open(path: &Path) -> Result<EnvironmentMap, EnvironmentError> {
+        match HdrFormat::from_path(path) {
+            Some(HdrFormat::Radiance) => {
+                let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+                let meta = decoder.metadata();
+                let pixels = decoder
+                    .read_image_hdr()?
+                    .iter()
+                    .map(|px| RGB::new(px[0] as f64, px[1] as f64, px[2] as f64))
+                    .collect();
+                Ok(EnvironmentMap::new(
+                    meta.width as usize,
+                    meta.height as usize,
+                    pixels,
+                ))
+            }
+            Some(HdrFormat::OpenExr) => {
+                let image = exr::prelude::read_first_rgba_layer_from_file(
+                    path,
+                    |size, _| (size.width(), vec![RGB::new(0., 0., 0.); size.area()]),
+                    |(width, pixels), pos, (r, g, b, _): (f32, f32, f32, f32)| {
+                        pixels[pos.y() * *width + pos.x()] = RGB::new(r as f64, g as f64, b as f64)
+                    },
+                )?;
+                let size = image.layer_data.size;
+                let (_, pixels) = image.layer_data.channel_data.pixels;
+                Ok(EnvironmentMap::new(size.width(), size.height(), pixels))
+            }
+            _ => {
+                let image = image::open(path)?.to_rgb();
+                let decode = |c: u8| tonemap::srgb_decode(c as f64 / 255.);
+                let pixels = image
+                    .pixels()
+                    .map(|px| RGB::new(decode(px[0]), decode(px[1]), decode(px[2])))
+                    .collect();
+                Ok(EnvironmentMap::new(
+                    image.width() as usize,
+                    image.height() as usize,
+                    pixels,
+                ))
+            }
+        }
+    }
+
+    // Counterclockwise rotation around the y axis, in degrees
+    pub fn rotation(mut self, degrees: f64) -> Self {
+        self.rotation = Transform::rotate_y(degrees);
+        self
+    }
+
+    // Scales the radiance of every pixel
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    // Pixel seen in direction `dir`
+    fn pixel(&self, dir: &Vector3) -> (usize, usize) {
+        let local = self.rotation.inverse.vector(*dir).unit_vec();
+        let (u, v) = Sphere::get_uv(local);
+        let col = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = (((1. - v) * self.height as f64) as usize).min(self.height - 1);
+        (col, row)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64> {
+        let (col, row) = self.pixel(dir);
+        self.pixels[row * self.width + col] * self.intensity
+    }
+
+    // The image covers 2pi by pi radians, and a pixel's solid angle shrinks with
+    // sin(theta) towards the poles
+    fn pdf_value(&self, dir: &Vector3) -> f64 {
+        let (col, row) = self.pixel(dir);
+        let y = self.rotation.inverse.vector(*dir).unit_vec().y;
+        let sin = (1. - y * y).max(0.).sqrt();
+        if sin <= 0. {
+            return 0.;
+        }
+        self.distribution.pdf(col, row) / (2. * PI * PI * sin)
+    }
+
+    fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vector3 {
+        // Inverse of `Sphere::get_uv`
+        let ((u, t), _) = self.distribution.sample(sampler.get_2d());
+        let phi = (1. - u) * 2. * PI - PI;
+        let theta = (1. - t) * PI - PI / 2.;
+        let local = Vector3::new(
+            theta.cos() * phi.cos(),
+            theta.sin(),
+            theta.cos() * phi.sin(),
+        );
+        self.rotation.vector(local)
+    }
+}
+
+// Sine of the angle from straight up at the center of `row`
+fn sin_theta(row: usize, height: usize) -> f64 {
+    (PI * (row as f64 + 0.5) / height as f64).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::independent::IndependentSampler;
+
+    // Dim sky brightening towards the top, with a bright sun-like pixel
+    fn test_map() -> EnvironmentMap {
+        let (width, height) = (16, 8);
+        let mut pixels: Vec<RGB<f64>> = (0..width * height)
+            .map(|i| {
+                let l = 0.1 + (height - i / width) as f64 * 0.1;
+                RGB::new(l, l * 0.9, l * 1.2)
+            })
+            .collect();
+        pixels[2 * width + 5] = RGB::new(20., 18., 15.);
+        EnvironmentMap::new(width, height, pixels).rotation(30.)
+    }
+
+    // Integrates `f` over the sphere with the midpoint rule on a fine grid
+    fn integrate(f: impl Fn(&Vector3) -> f64) -> f64 {
+        let (n_phi, n_theta) = (1024, 512);
+        let (d_phi, d_theta) = (2. * PI / n_phi as f64, PI / n_theta as f64);
+        let mut sum = 0.;
+        for i in 0..n_theta {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..n_phi {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let dir = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                sum += f(&dir) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let map = test_map();
+        let integral = integrate(|dir| map.pdf_value(dir));
+        assert!((integral - 1.).abs() < 0.005, "integral was {}", integral);
+    }
+
+    // Averaging f / pdf over sampled directions estimates the integral of f, which only
+    // works out if the samples are drawn with the density pdf_value reports
+    #[test]
+    fn sampled_directions_match_pdf() {
+        let map = test_map();
+        let luminance = |dir: &Vector3| tonemap::luminance(map.radiance(dir));
+        let expected = integrate(luminance);
+
+        let mut sampler = IndependentSampler::new();
+        sampler.start_sample(3, (0, 0), 0, 1);
+        let n = 200_000;
+        let (mut solid_angle, mut integral) = (0., 0.);
+        for _ in 0..n {
+            let dir = map.sample_direction(&mut sampler);
+            let pdf = map.pdf_value(&dir);
+            assert!(pdf > 0.);
+            solid_angle += 1. / pdf;
+            integral += luminance(&dir) / pdf;
+        }
+        let solid_angle = solid_angle / n as f64;
+        let integral = integral / n as f64;
+        assert!(
+            (solid_angle / (4. * PI) - 1.).abs() < 0.01,
+            "solid angle was {}",
+            solid_angle
+        );
+        assert!(
+            (integral / expected - 1.).abs() < 0.01,
+            "{} vs {}",
+            integral,
+            expected
+        );
+    }
+}
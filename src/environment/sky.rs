@@ -0,0 +1,286 @@
+// Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight" (1999)
+use crate::environment::Environment;
+use crate::onb::Onb;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use rgb::RGB;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+// The model gives luminance in kcd/m^2. This puts a white surface in the
+// midday sun at about 1.
+const LUMINANCE_SCALE: f64 = 0.02;
+
+// Approximate luminance of the sun outside the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: f64 = 2.0e6;
+
+// Apparent size of the sun from earth, in degrees
+pub const SUN_DIAMETER: f64 = 0.53;
+
+// Clear sky lit by the sun, over a diffuse ground lit by both
+pub struct PreethamSky {
+    sun_dir: Vector3,
+    // Angle between the sun and straight up
+    theta_sun: f64,
+    // Perez distribution coefficients for Y, x and y
+    perez: [[f64; 5]; 3],
+    // Y, x and y straight up
+    zenith: [f64; 3],
+    ground: RGB<f64>,
+    sun: Option<Sun>,
+    intensity: f64,
+}
+
+// Disc of constant radiance infinitely far away
+pub struct Sun {
+    dir: Vector3,
+    cos_max: f64,
+    radiance: RGB<f64>,
+}
+
+impl PreethamSky {
+    // `sun_dir` points towards the sun. Turbidity is the haziness of the atmosphere,
+    // from about 2 (very clear) to 10 (hazy). Includes a `Sun` of matching color.
+    pub fn new(sun_dir: Vector3, turbidity: f64, ground_albedo: RGB<f64>) -> PreethamSky {
+        let sun_dir = sun_dir.unit_vec();
+        // The model breaks down with the sun below the horizon
+        let theta_sun = sun_dir.y.clamp(-1., 1.).acos().min(FRAC_PI_2 - 0.01);
+        let t = turbidity;
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_sun);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_chromaticity = |m: [[f64; 4]; 3]| {
+            let ts = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.];
+            let row = |r: [f64; 4]| (0..4).map(|i| r[i] * ts[i]).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_yc = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let sun = if sun_dir.y > 0. {
+            Some(Sun::through_atmosphere(sun_dir, SUN_DIAMETER, turbidity))
+        } else {
+            None
+        };
+        let mut sky = PreethamSky {
+            sun_dir,
+            theta_sun,
+            perez,
+            zenith: [zenith_y, zenith_x, zenith_yc],
+            ground: RGB::new(0., 0., 0.),
+            sun,
+            intensity: 1.,
+        };
+        let e = sky.irradiance();
+        let a = ground_albedo;
+        sky.ground = RGB::new(a.r * e.r, a.g * e.g, a.b * e.b) * (1. / PI);
+        sky
+    }
+
+    // Angular diameter of the sun in degrees; 0 leaves only the sky
+    pub fn sun_diameter(mut self, degrees: f64) -> Self {
+        if degrees <= 0. {
+            self.sun = None;
+        } else if let Some(sun) = &self.sun {
+            // Keep the power the same, so only the shadows change
+            let power = sun.radiance * sun.solid_angle();
+            let radiance = power * (1. / cone_solid_angle(degrees));
+            self.sun = Some(Sun::new(sun.dir, degrees, radiance));
+        }
+        self
+    }
+
+    // Scales the sky, sun and ground together
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    fn sky(&self, dir: &Vector3) -> RGB<f64> {
+        let cos_theta = dir.y.max(0.001);
+        let gamma = dir.dot(&self.sun_dir).clamp(-1., 1.).acos();
+        let value = |i: usize| {
+            self.zenith[i] * perez(self.perez[i], cos_theta, gamma)
+                / perez(self.perez[i], 1., self.theta_sun)
+        };
+        xyy_to_rgb(value(1), value(2), value(0) * LUMINANCE_SCALE)
+    }
+
+    // Light falling on the ground from the sky and sun
+    fn irradiance(&self) -> RGB<f64> {
+        // Midpoint rule over the upper hemisphere
+        let (n_theta, n_phi) = (32, 64);
+        let (d_theta, d_phi) = (FRAC_PI_2 / n_theta as f64, 2. * PI / n_phi as f64);
+        let mut sum = RGB::new(0., 0., 0.);
+        for i in 0..n_theta {
+            let theta = (i as f64 + 0.5) * d_theta;
+            let (sin, cos) = theta.sin_cos();
+            for j in 0..n_phi {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let dir = Vector3::new(sin * phi.cos(), cos, sin * phi.sin());
+                sum += self.sky(&dir) * (cos * sin * d_theta * d_phi);
+            }
+        }
+        if let Some(sun) = &self.sun {
+            sum += sun.radiance * (sun.solid_angle() * sun.dir.y);
+        }
+        sum
+    }
+}
+
+impl Environment for PreethamSky {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64> {
+        let dir = dir.unit_vec();
+        if dir.y < 0. {
+            return self.ground * self.intensity;
+        }
+        let mut radiance = self.sky(&dir);
+        if let Some(sun) = &self.sun {
+            radiance += sun.radiance(&dir);
+        }
+        radiance * self.intensity
+    }
+
+    // Only the sun is sampled, the sky is smooth enough to be found by BSDF sampling
+    fn pdf_value(&self, dir: &Vector3) -> f64 {
+        match &self.sun {
+            Some(sun) => sun.pdf_value(dir),
+            None => 0.,
+        }
+    }
+
+    fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vector3 {
+        match &self.sun {
+            Some(sun) => sun.sample_direction(sampler),
+            None => Vector3::new(1., 0., 0.),
+        }
+    }
+}
+
+impl Sun {
+    // `dir` points towards the sun, `diameter` is its angular diameter in degrees
+    pub fn new(dir: Vector3, diameter: f64, radiance: RGB<f64>) -> Sun {
+        Sun {
+            dir: dir.unit_vec(),
+            cos_max: (diameter.to_radians() / 2.).cos(),
+            radiance,
+        }
+    }
+
+    // The sun as seen through `turbidity`, reddened as it gets lower.
+    // Extinction is only evaluated at one wavelength per color channel.
+    pub fn through_atmosphere(dir: Vector3, diameter: f64, turbidity: f64) -> Sun {
+        let dir = dir.unit_vec();
+        let theta = dir.y.clamp(0., 1.).acos();
+        // Relative optical mass of air along the path to the sun
+        let mass = 1. / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+        // Angstrom turbidity coefficient for aerosols
+        let beta = 0.04608 * turbidity - 0.04586;
+        let transmittance = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+            rayleigh * aerosol
+        };
+        let radiance = RGB::new(
+            transmittance(0.68),
+            transmittance(0.55),
+            transmittance(0.44),
+        ) * (SUN_LUMINANCE * LUMINANCE_SCALE);
+        Sun::new(dir, diameter, radiance)
+    }
+
+    pub fn intensity(mut self, intensity: f64) -> Self {
+        self.radiance *= intensity;
+        self
+    }
+
+    fn solid_angle(&self) -> f64 {
+        2. * PI * (1. - self.cos_max)
+    }
+}
+
+impl Environment for Sun {
+    fn radiance(&self, dir: &Vector3) -> RGB<f64> {
+        if dir.unit_vec().dot(&self.dir) >= self.cos_max {
+            self.radiance
+        } else {
+            RGB::new(0., 0., 0.)
+        }
+    }
+
+    fn pdf_value(&self, dir: &Vector3) -> f64 {
+        if dir.unit_vec().dot(&self.dir) >= self.cos_max {
+            1. / self.solid_angle()
+        } else {
+            0.
+        }
+    }
+
+    // Uniform over the cone the sun covers
+    fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vector3 {
+        let (r1, r2) = sampler.get_2d();
+        let z = 1. + r2 * (self.cos_max - 1.);
+        let phi = 2. * PI * r1;
+        let sin_theta = (1. - z * z).sqrt();
+        Onb::from_w(&self.dir).local(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+    }
+}
+
+// Solid angle of a disc with the given angular diameter in degrees
+fn cone_solid_angle(diameter: f64) -> f64 {
+    2. * PI * (1. - (diameter.to_radians() / 2.).cos())
+}
+
+// Perez et al. all-weather luminance distribution
+fn perez(c: [f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let cos_gamma = gamma.cos();
+    (1. + c[0] * (c[1] / cos_theta).exp())
+        * (1. + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+// CIE xyY to linear sRGB
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> RGB<f64> {
+    if y <= 0. {
+        return RGB::new(0., 0., 0.);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1. - x - y) / y * luminance;
+    // Colors outside the sRGB gamut are clipped
+    RGB::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.),
+    )
+}
@@ -0,0 +1,115 @@
+use crate::filters::Filter;
+
+use rgb::RGB;
+
+// Accumulates filtered samples for a range of rows of the image. Coordinates are
+// in pixels with y pointing up, like the camera's u and v.
+#[derive(Clone)]
+pub struct Film {
+    width: u32,
+    // First row covered
+    y0: u32,
+    rows: u32,
+    filter: Filter,
+    pub(crate) pixels: Vec<FilmPixel>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct FilmPixel {
+    // Sum of the filter weighted samples
+    pub color: RGB<f64>,
+    pub weight: f64,
+    // Samples taken inside this pixel, not counting ones splatted from neighbors
+    pub samples: u32,
+}
+
+impl FilmPixel {
+    // Weighted average of the samples, black if nothing landed here
+    pub fn value(&self) -> RGB<f64> {
+        if self.weight == 0. {
+            return RGB::new(0., 0., 0.);
+        }
+        self.color * (1. / self.weight)
+    }
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Film {
+        Film::rows(width, 0, height, filter)
+    }
+
+    fn rows(width: u32, y0: u32, rows: u32, filter: Filter) -> Film {
+        Film {
+            width,
+            y0,
+            rows,
+            filter,
+            pixels: vec![FilmPixel::default(); (width * rows) as usize],
+        }
+    }
+
+    // Pixels a sample can reach on either side of the one it's in
+    fn reach(&self) -> u32 {
+        ((self.filter.radius() + 0.5).ceil() as u32).saturating_sub(1)
+    }
+
+    // Empty film covering every pixel that samples taken in `rows` rows starting at
+    // `first` can reach
+    pub fn strip(&self, first: u32, rows: u32) -> Film {
+        let reach = self.reach();
+        let start = first.saturating_sub(reach).max(self.y0);
+        let end = (first + rows + reach).min(self.y0 + self.rows);
+        Film::rows(self.width, start, end - start, self.filter)
+    }
+
+    // Adds a sample at `pos` to every pixel in the filter's footprint
+    pub fn add_sample(&mut self, pos: (f64, f64), color: RGB<f64>) {
+        let reach = self.reach() as i64;
+        let (col, row) = (pos.0.floor() as i64, pos.1.floor() as i64);
+        let (y0, y1) = (self.y0 as i64, (self.y0 + self.rows) as i64);
+        if (0..self.width as i64).contains(&col) && (y0..y1).contains(&row) {
+            self.pixel_mut(col as u32, row as u32).samples += 1;
+        }
+
+        for y in (row - reach).max(y0)..(row + reach + 1).min(y1) {
+            for x in (col - reach).max(0)..(col + reach + 1).min(self.width as i64) {
+                let offset = (pos.0 - (x as f64 + 0.5), pos.1 - (y as f64 + 0.5));
+                let weight = self.filter.evaluate(offset.0, offset.1);
+                if weight != 0. {
+                    let pixel = self.pixel_mut(x as u32, y as u32);
+                    pixel.color += color * weight;
+                    pixel.weight += weight;
+                }
+            }
+        }
+    }
+
+    // Adds everything in `other` to the overlapping pixels here
+    pub fn merge(&mut self, other: &Film) {
+        for row in other.y0..other.y0 + other.rows {
+            if row < self.y0 || row >= self.y0 + self.rows {
+                continue;
+            }
+            for col in 0..self.width {
+                let src = other.pixel(col, row);
+                let dst = self.pixel_mut(col, row);
+                dst.color += src.color;
+                dst.weight += src.weight;
+                dst.samples += src.samples;
+            }
+        }
+    }
+
+    pub fn pixel(&self, col: u32, row: u32) -> FilmPixel {
+        self.pixels[((row - self.y0) * self.width + col) as usize]
+    }
+
+    fn pixel_mut(&mut self, col: u32, row: u32) -> &mut FilmPixel {
+        &mut self.pixels[((row - self.y0) * self.width + col) as usize]
+    }
+
+    // Every pixel in rows from top to bottom
+    pub fn pixels(&self) -> impl Iterator<Item = &FilmPixel> {
+        self.pixels.chunks(self.width as usize).rev().flatten()
+    }
+}
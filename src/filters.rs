@@ -0,0 +1,136 @@
+use std::f64::consts::PI;
+
+// Weights a sample by its offset in pixels from the center of each pixel it lands
+// near. Samples count towards every pixel within `radius` along both axes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Filter {
+    // Every sample inside the footprint counts the same. With a radius of 0.5
+    // this is plain per-pixel averaging.
+    Box { radius: f64 },
+    // Falls off linearly to the edge
+    Tent { radius: f64 },
+    // Gaussian with a standard deviation of a third of the radius, shifted down to
+    // reach zero at the edge
+    Gaussian { radius: f64 },
+    // Mitchell-Netravali cubic with B = C = 1/3. Has small negative lobes.
+    Mitchell { radius: f64 },
+    // Sinc windowed by a sinc stretched to the radius. Sharpest, but can ring.
+    Lanczos { radius: f64 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub const NAMES: [&'static str; 5] = ["box", "tent", "gaussian", "mitchell", "lanczos"];
+
+    // Each filter has its own default radius
+    pub fn from_name(name: &str, radius: Option<f64>) -> Option<Filter> {
+        match name {
+            "box" => Some(Filter::Box {
+                radius: radius.unwrap_or(0.5),
+            }),
+            "tent" => Some(Filter::Tent {
+                radius: radius.unwrap_or(1.),
+            }),
+            "gaussian" => Some(Filter::Gaussian {
+                radius: radius.unwrap_or(1.5),
+            }),
+            "mitchell" => Some(Filter::Mitchell {
+                radius: radius.unwrap_or(2.),
+            }),
+            "lanczos" => Some(Filter::Lanczos {
+                radius: radius.unwrap_or(3.),
+            }),
+            _ => None,
+        }
+    }
+
+    // As given to `from_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Box { .. } => "box",
+            Filter::Tent { .. } => "tent",
+            Filter::Gaussian { .. } => "gaussian",
+            Filter::Mitchell { .. } => "mitchell",
+            Filter::Lanczos { .. } => "lanczos",
+        }
+    }
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius }
+            | Filter::Mitchell { radius }
+            | Filter::Lanczos { radius } => radius,
+        }
+    }
+
+    // Same filter with a different radius
+    pub fn with_radius(self, radius: f64) -> Filter {
+        match self {
+            Filter::Box { .. } => Filter::Box { radius },
+            Filter::Tent { .. } => Filter::Tent { radius },
+            Filter::Gaussian { .. } => Filter::Gaussian { radius },
+            Filter::Mitchell { .. } => Filter::Mitchell { radius },
+            Filter::Lanczos { .. } => Filter::Lanczos { radius },
+        }
+    }
+
+    // Weight of a sample `dx`, `dy` pixels away from a pixel center
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let r = self.radius();
+        match *self {
+            // Half open, so samples on a pixel's edge only land in one pixel
+            Filter::Box { .. } => {
+                if x >= -r && x < r {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            _ if x.abs() >= r => 0.,
+            Filter::Tent { .. } => r - x.abs(),
+            Filter::Gaussian { .. } => {
+                let sigma = r / 3.;
+                let gaussian = |x: f64| (-x * x / (2. * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(r)).max(0.)
+            }
+            Filter::Mitchell { .. } => mitchell(2. * x / r),
+            Filter::Lanczos { .. } => sinc(x) * sinc(x / r),
+        }
+    }
+}
+
+// Mitchell-Netravali cubic with B = C = 1/3 over [-2, 2]
+fn mitchell(x: f64) -> f64 {
+    let (b, c) = (1. / 3., 1. / 3.);
+    let x = x.abs();
+    let value = if x < 1. {
+        (12. - 9. * b - 6. * c) * x * x * x + (-18. + 12. * b + 6. * c) * x * x + (6. - 2. * b)
+    } else if x < 2. {
+        (-b - 6. * c) * x * x * x
+            + (6. * b + 30. * c) * x * x
+            + (-12. * b - 48. * c) * x
+            + (8. * b + 24. * c)
+    } else {
+        0.
+    };
+    value / 6.
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.;
+    }
+    let px = PI * x;
+    px.sin() / px
+}
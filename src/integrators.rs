@@ -0,0 +1,36 @@
+pub mod debug;
+pub mod path;
+
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::world::World;
+
+use rgb::RGB;
+
+// Estimates the light arriving along camera rays
+pub trait Integrator: Sync + Send {
+    // As given to `from_name`
+    fn name(&self) -> &'static str;
+
+    // All randomness comes from `sampler`, so renders can be reproduced
+    fn radiance(&self, world: &World, ray: &Ray, sampler: &mut dyn Sampler) -> RGB<f64>;
+}
+
+pub const NAMES: [&str; 7] = ["path", "ao", "normals", "uv", "albedo", "depth", "bounces"];
+
+pub fn from_name(name: &str) -> Option<Box<dyn Integrator>> {
+    match name {
+        "path" => Some(Box::new(path::PathTracer)),
+        "ao" => Some(Box::new(debug::AmbientOcclusion { radius: None })),
+        "normals" => Some(Box::new(debug::Normals)),
+        "uv" => Some(Box::new(debug::Uv)),
+        "albedo" => Some(Box::new(debug::Albedo)),
+        "depth" => Some(Box::new(debug::Depth)),
+        "bounces" => Some(Box::new(debug::BounceHeatmap)),
+        _ => None,
+    }
+}
+
+pub(crate) fn multiply(a: RGB<f64>, b: RGB<f64>) -> RGB<f64> {
+    RGB::new(a.r * b.r, a.g * b.g, a.b * b.b)
+}
@@ -0,0 +1,124 @@
+// Integrators that show what the geometry and materials are doing instead of lighting
+use crate::integrators::{path::PathTracer, Integrator};
+use crate::objects::Hittable;
+use crate::pdf::{CosinePdf, Pdf};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::tonemap;
+use crate::world::World;
+
+use rgb::RGB;
+
+// White where nothing is hit within `radius` of a cosine-weighted direction.
+// Without a radius, a quarter of the scene's bounding box diagonal is used.
+pub struct AmbientOcclusion {
+    pub radius: Option<f64>,
+}
+
+// Shading normal at the first hit, mapped from [-1, 1] to [0, 1]
+pub struct Normals;
+
+// Texture coordinates at the first hit as red and green
+pub struct Uv;
+
+// Surface color at the first hit, without any lighting
+pub struct Albedo;
+
+// Distance to the first hit. Written to .exr, .hdr or .pfm it's exact;
+// for 8-bit output use a negative exposure to bring it into range.
+pub struct Depth;
+
+// Number of bounces the path tracer makes, from blue (none) to red (`max_depth`)
+pub struct BounceHeatmap;
+
+impl Integrator for AmbientOcclusion {
+    fn name(&self) -> &'static str {
+        "ao"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, sampler: &mut dyn Sampler) -> RGB<f64> {
+        let hr = match world.objects.intersect(ray, 0.001) {
+            Some(hr) => hr,
+            None => return RGB::new(1., 1., 1.),
+        };
+        let radius = self.radius.unwrap_or_else(|| match world.objects.aabb() {
+            Some(bbox) => 0.25 * (bbox.max - bbox.min).length(),
+            None => f64::INFINITY,
+        });
+
+        let dir = CosinePdf::new(&hr.normal).generate(sampler).unit_vec();
+        match world.objects.intersect(&Ray::new(hr.hit_point, dir), 0.001) {
+            Some(occluder) if occluder.dist <= radius => RGB::new(0., 0., 0.),
+            _ => RGB::new(1., 1., 1.),
+        }
+    }
+}
+
+impl Integrator for Normals {
+    fn name(&self) -> &'static str {
+        "normals"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, _sampler: &mut dyn Sampler) -> RGB<f64> {
+        match world.objects.intersect(ray, 0.001) {
+            Some(hr) => {
+                let n = hr.normal;
+                RGB::new(n.x + 1., n.y + 1., n.z + 1.) * 0.5
+            }
+            None => RGB::new(0., 0., 0.),
+        }
+    }
+}
+
+impl Integrator for Uv {
+    fn name(&self) -> &'static str {
+        "uv"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, _sampler: &mut dyn Sampler) -> RGB<f64> {
+        match world.objects.intersect(ray, 0.001) {
+            Some(hr) => RGB::new(hr.u, hr.v, 0.),
+            None => RGB::new(0., 0., 0.),
+        }
+    }
+}
+
+impl Integrator for Albedo {
+    fn name(&self) -> &'static str {
+        "albedo"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, _sampler: &mut dyn Sampler) -> RGB<f64> {
+        match world.objects.intersect(ray, 0.001) {
+            Some(hr) => hr.mat.albedo(&hr),
+            None => RGB::new(0., 0., 0.),
+        }
+    }
+}
+
+impl Integrator for Depth {
+    fn name(&self) -> &'static str {
+        "depth"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, _sampler: &mut dyn Sampler) -> RGB<f64> {
+        match world.objects.intersect(ray, 0.001) {
+            Some(hr) => {
+                let distance = hr.dist * ray.dir.length();
+                RGB::new(distance, distance, distance)
+            }
+            None => RGB::new(0., 0., 0.),
+        }
+    }
+}
+
+impl Integrator for BounceHeatmap {
+    fn name(&self) -> &'static str {
+        "bounces"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, sampler: &mut dyn Sampler) -> RGB<f64> {
+        let (_, bounces) = PathTracer.trace(world, ray, sampler);
+        tonemap::heat(bounces as f64 / world.max_depth.max(1) as f64)
+    }
+}
@@ -0,0 +1,155 @@
+use crate::integrators::{multiply, Integrator};
+use crate::objects::{HitResult, Hittable};
+use crate::pdf;
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::world::World;
+
+use rgb::RGB;
+
+// Unidirectional path tracing with next event estimation and Russian roulette
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn name(&self) -> &'static str {
+        "path"
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray, sampler: &mut dyn Sampler) -> RGB<f64> {
+        self.trace(world, ray, sampler).0
+    }
+}
+
+impl PathTracer {
+    // Radiance along `ray` and the number of bounces the path made
+    pub fn trace(&self, world: &World, ray: &Ray, sampler: &mut dyn Sampler) -> (RGB<f64>, u32) {
+        let mut radiance = RGB::new(0., 0., 0.);
+        let mut throughput = RGB::new(1., 1., 1.);
+        let mut ray = Ray::new(ray.origin, ray.dir);
+        // Density the last bounce sampled `ray` with, or None if emission found by the
+        // ray should be counted in full
+        let mut bsdf_pdf = None;
+
+        let mut bounces = 0;
+
+        for depth in 0..world.max_depth {
+            let hr = match world.objects.intersect(&ray, 0.001) {
+                Some(hr) => hr,
+                None => {
+                    let mut background = world.environment.radiance(&ray.dir);
+                    if let Some(pdf) = bsdf_pdf {
+                        let env_pdf = world.environment.pdf_value(&ray.dir);
+                        background *= pdf::power_heuristic(pdf, env_pdf);
+                    }
+                    radiance += multiply(throughput, background);
+                    break;
+                }
+            };
+
+            let mut emitted = hr.mat.emitted(hr.u, hr.v, hr.hit_point);
+            if let Some(pdf) = bsdf_pdf {
+                // This light could also have been reached by direct light sampling
+                let light_pdf = world.lights.pdf_value(&ray.origin, &ray.dir);
+                emitted *= pdf::power_heuristic(pdf, light_pdf);
+            }
+            radiance += multiply(throughput, emitted);
+
+            let mr = match hr.mat.scatter(&ray, &hr, sampler) {
+                Some(mr) => mr,
+                None => break,
+            };
+
+            // Monte Carlo estimate: f * L * cos(theta) / pdf
+            let weight = match mr.pdf {
+                Some(pdf) => {
+                    let cosine = mr.scattered.dir.unit_vec().dot(&hr.normal).abs();
+                    mr.attenuation * (cosine / pdf)
+                }
+                None => mr.attenuation,
+            };
+            if mr.pdf.is_some() {
+                if !world.lights.objects.is_empty() {
+                    radiance += multiply(throughput, sample_lights(world, &ray, &hr, sampler));
+                }
+                radiance += multiply(throughput, sample_environment(world, &ray, &hr, sampler));
+            }
+            throughput = multiply(throughput, weight);
+
+            // Russian roulette: keep the path with a probability that follows its
+            // throughput and boost the survivors to stay unbiased
+            if depth + 1 >= world.rr_depth {
+                let survival = max_component(throughput).min(0.95);
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput *= 1. / survival;
+            }
+
+            ray = mr.scattered;
+            bsdf_pdf = mr.pdf;
+            bounces += 1;
+        }
+        (radiance, bounces)
+    }
+}
+
+// Next event estimation: light arriving directly from a point sampled on the lights,
+// weighted against finding the same light through BSDF sampling
+fn sample_lights(world: &World, ray: &Ray, hr: &HitResult, sampler: &mut dyn Sampler) -> RGB<f64> {
+    let black = RGB::new(0., 0., 0.);
+    let dir = world.lights.sample_direction(&hr.hit_point, sampler);
+    let light_pdf = world.lights.pdf_value(&hr.hit_point, &dir);
+    if light_pdf <= 0. {
+        return black;
+    }
+    let f = hr.mat.bsdf(ray, hr, &dir);
+    if f == black {
+        return black;
+    }
+
+    let shadow_ray = Ray::new(hr.hit_point, dir);
+    let emitted = match world.objects.intersect(&shadow_ray, 0.001) {
+        Some(light) => light.mat.emitted(light.u, light.v, light.hit_point),
+        None => return black,
+    };
+
+    let bsdf_pdf = hr.mat.pdf(ray, hr, &dir);
+    let cosine = dir.unit_vec().dot(&hr.normal).abs();
+    let weight = pdf::power_heuristic(light_pdf, bsdf_pdf) * cosine / light_pdf;
+    multiply(f, emitted) * weight
+}
+
+// Same as `sample_lights`, for light from the environment
+fn sample_environment(
+    world: &World,
+    ray: &Ray,
+    hr: &HitResult,
+    sampler: &mut dyn Sampler,
+) -> RGB<f64> {
+    let black = RGB::new(0., 0., 0.);
+    let dir = world.environment.sample_direction(sampler);
+    let env_pdf = world.environment.pdf_value(&dir);
+    if env_pdf <= 0. {
+        return black;
+    }
+    let f = hr.mat.bsdf(ray, hr, &dir);
+    if f == black {
+        return black;
+    }
+    if world
+        .objects
+        .intersect(&Ray::new(hr.hit_point, dir), 0.001)
+        .is_some()
+    {
+        return black;
+    }
+
+    let bsdf_pdf = hr.mat.pdf(ray, hr, &dir);
+    let cosine = dir.unit_vec().dot(&hr.normal).abs();
+    let weight = pdf::power_heuristic(env_pdf, bsdf_pdf) * cosine / env_pdf;
+    multiply(f, world.environment.radiance(&dir)) * weight
+}
+
+fn max_component(color: RGB<f64>) -> f64 {
+    color.r.max(color.g).max(color.b)
+}
@@ -0,0 +1,35 @@
+//! A path tracer based on *Ray Tracing in One Weekend*.
+//!
+//! Build a [`World`] with [`World::builder`], add objects to it and call [`World::run`]
+//! to render it to an image file, or load one from a scene file with [`scene::load`].
+
+pub mod adaptive;
+pub mod camera;
+pub mod checkpoint;
+pub mod distribution;
+pub mod environment;
+pub mod film;
+pub mod filters;
+pub mod integrators;
+pub mod materials;
+pub mod obj;
+pub mod objects;
+pub mod onb;
+pub mod output;
+pub mod pdf;
+pub mod progressive;
+pub mod ray;
+pub mod samplers;
+pub mod scene;
+pub mod textures;
+pub mod tonemap;
+pub mod transform;
+pub mod vec3;
+pub mod world;
+
+pub use crate::camera::Camera;
+pub use crate::environment::Environment;
+pub use crate::materials::Material;
+pub use crate::objects::Hittable;
+pub use crate::textures::Texture;
+pub use crate::world::{World, WorldBuilder};
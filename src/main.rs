@@ -1,28 +1,333 @@
-mod camera;
-mod materials;
-mod objects;
-mod ray;
-mod textures;
-mod vec3;
-mod world;
-
-use crate::materials::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal};
-use crate::objects::sphere::Sphere;
-use crate::textures::color::SolidColor;
-use crate::vec3::Vector3;
-use crate::world::World;
+use raytracer::adaptive::Adaptive;
+use raytracer::checkpoint::{Checkpoint, CheckpointError};
+use raytracer::filters::Filter;
+use raytracer::integrators;
+use raytracer::materials::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal};
+use raytracer::objects::sphere::Sphere;
+use raytracer::output::OutputError;
+use raytracer::samplers;
+use raytracer::scene;
+use raytracer::textures::color::SolidColor;
+use raytracer::tonemap::ToneMap;
+use raytracer::vec3::Vector3;
+use raytracer::World;
 
 use rand::Rng;
 use rgb::RGB;
+use std::path::PathBuf;
+use std::time::Duration;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+#[structopt(about = "Renders a scene file, or the built-in scene if none is given")]
+struct Opt {
+    /// Scene description file (TOML)
+    #[structopt(parse(from_os_str))]
+    scene: Option<PathBuf>,
+
+    /// Render a new random field of spheres instead of the built-in scene
+    #[structopt(long, conflicts_with = "scene")]
+    random_scene: bool,
+
+    /// Output image
+    #[structopt(short, long, default_value = "out.png", parse(from_os_str))]
+    output: PathBuf,
+
+    /// Image width in pixels. Keeps the scene's aspect ratio unless --height is also given
+    #[structopt(long)]
+    width: Option<u32>,
+
+    /// Image height in pixels. Keeps the scene's aspect ratio unless --width is also given
+    #[structopt(long)]
+    height: Option<u32>,
+
+    /// Samples per pixel
+    #[structopt(short, long)]
+    spp: Option<u32>,
+
+    /// Keep sampling pixels whose relative error is above this, up to --max-spp
+    #[structopt(long)]
+    adaptive_threshold: Option<f64>,
+
+    /// Most samples an adaptively sampled pixel may take (defaults to 8 times --spp)
+    #[structopt(long)]
+    max_spp: Option<u32>,
+
+    /// Also save an image of the number of samples in each pixel
+    #[structopt(long, parse(from_os_str))]
+    sample_map: Option<PathBuf>,
+
+    /// Render in passes of this many samples per pixel, saving the image after each
+    #[structopt(long)]
+    pass_spp: Option<u32>,
+
+    /// Save the image at most this often during a progressive render, in seconds
+    #[structopt(long)]
+    snapshot_interval: Option<f64>,
+
+    /// Stop a progressive render after this many seconds
+    #[structopt(long)]
+    time_limit: Option<f64>,
+
+    /// Save the render's progress here with every snapshot. Makes the render progressive.
+    #[structopt(long, parse(from_os_str))]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue the render saved in --checkpoint, with the same scene and options
+    #[structopt(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Reconstruction filter used to weight samples into pixels
+    #[structopt(long, possible_values = &Filter::NAMES)]
+    filter: Option<String>,
+
+    /// Filter radius in pixels (each filter has its own default)
+    #[structopt(long)]
+    filter_radius: Option<f64>,
+
+    /// Maximum number of bounces per path
+    #[structopt(short = "d", long)]
+    max_depth: Option<u32>,
+
+    /// Bounces before paths may be terminated by Russian roulette
+    #[structopt(long)]
+    rr_depth: Option<u32>,
+
+    /// Light transport algorithm
+    #[structopt(long, possible_values = &integrators::NAMES)]
+    integrator: Option<String>,
+
+    /// Source of the random numbers for each sample
+    #[structopt(long, possible_values = &samplers::NAMES)]
+    sampler: Option<String>,
+
+    /// Number of render threads (defaults to one per core)
+    #[structopt(short = "j", long)]
+    threads: Option<usize>,
+
+    /// Seed for the random number generator
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Tone mapping operator for 8-bit output
+    #[structopt(long, possible_values = &ToneMap::NAMES)]
+    tone_map: Option<String>,
+
+    /// Luminance mapped to white by the extended_reinhard tone map
+    /// [default: the scene's, or 4.0]
+    #[structopt(long)]
+    white_point: Option<f64>,
+
+    /// Exposure adjustment in stops
+    #[structopt(long)]
+    exposure: Option<f64>,
+}
 
 fn main() {
-    let filename = "out.png";
-    let mut world = World::new();
+    let opt = Opt::from_args();
+
+    let mut world = match &opt.scene {
+        Some(path) => match scene::load(path) {
+            Ok((world, warnings)) => {
+                for warning in warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                world
+            }
+            Err(e) => {
+                eprintln!("Failed to load {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None if opt.random_scene => random_scene(),
+        None => default_scene(),
+    };
+
+    if let Err(e) = apply_options(&opt, &mut world) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
     eprintln!(
         "{} x {}\nOutput file: {}",
-        world.width, world.height, filename
+        world.width,
+        world.height,
+        opt.output.display()
     );
 
+    world.on_pass = Some(Box::new(|pass| {
+        eprintln!(
+            "{} / {} spp in {:.1}s",
+            pass.spp,
+            pass.max_spp,
+            pass.elapsed.as_secs_f64()
+        )
+    }));
+
+    let output = opt.output.to_string_lossy();
+    let result = match (&opt.checkpoint, opt.resume) {
+        (Some(path), true) => match Checkpoint::load(path) {
+            Ok(checkpoint) => world.resume(&output, checkpoint),
+            Err(e) => {
+                eprintln!("Failed to load {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        _ => world.run(&output),
+    };
+    match result {
+        Err(e @ OutputError::Checkpoint(CheckpointError::Mismatch { .. })) => {
+            eprintln!("Can't resume the render: {}", e);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Failed to write {}: {}", opt.output.display(), e);
+            std::process::exit(1);
+        }
+        Ok(()) => {}
+    }
+    eprintln!("Done");
+}
+
+fn apply_options(opt: &Opt, world: &mut World) -> Result<(), String> {
+    if opt.width == Some(0) || opt.height == Some(0) {
+        return Err("Resolution must be greater than 0".to_string());
+    }
+    let aspect_ratio = world.width as f64 / world.height as f64;
+    match (opt.width, opt.height) {
+        (Some(width), Some(height)) => world.set_resolution(width, height),
+        (Some(width), None) => {
+            world.set_resolution(width, ((width as f64 / aspect_ratio) as u32).max(1))
+        }
+        (None, Some(height)) => {
+            world.set_resolution(((height as f64 * aspect_ratio) as u32).max(1), height)
+        }
+        (None, None) => {}
+    }
+
+    if let Some(spp) = opt.spp {
+        if spp == 0 {
+            return Err("Samples per pixel must be greater than 0".to_string());
+        }
+        world.spp = spp;
+    }
+    if let Some(threshold) = opt.adaptive_threshold {
+        if threshold <= 0. {
+            return Err("Adaptive threshold must be greater than 0".to_string());
+        }
+        let max_spp = match world.adaptive {
+            Some(adaptive) => adaptive.max_spp,
+            None => world.spp.saturating_mul(8),
+        };
+        world.adaptive = Some(Adaptive { threshold, max_spp });
+    }
+    if let Some(max_spp) = opt.max_spp {
+        match &mut world.adaptive {
+            Some(adaptive) => adaptive.max_spp = max_spp,
+            None => return Err("--max-spp needs --adaptive-threshold".to_string()),
+        }
+    }
+    if let Some(adaptive) = world.adaptive {
+        if adaptive.max_spp < world.spp {
+            return Err("Max samples per pixel must be at least --spp".to_string());
+        }
+    }
+    if opt.filter_radius.is_some_and(|r| r <= 0.) {
+        return Err("Filter radius must be greater than 0".to_string());
+    }
+    match (&opt.filter, opt.filter_radius) {
+        (Some(name), radius) => world.filter = Filter::from_name(name, radius).unwrap(),
+        (None, Some(radius)) => world.filter = world.filter.with_radius(radius),
+        (None, None) => {}
+    }
+    if opt.pass_spp.is_some()
+        || opt.snapshot_interval.is_some()
+        || opt.time_limit.is_some()
+        || opt.checkpoint.is_some()
+    {
+        let mut progressive = world.progressive.unwrap_or_default();
+        if let Some(pass_spp) = opt.pass_spp {
+            if pass_spp == 0 {
+                return Err("Samples per pass must be greater than 0".to_string());
+            }
+            progressive.pass_spp = pass_spp;
+        }
+        if let Some(interval) = opt.snapshot_interval {
+            progressive.snapshot_interval = Some(seconds(interval, "Snapshot interval")?);
+        }
+        if let Some(limit) = opt.time_limit {
+            progressive.time_limit = Some(seconds(limit, "Time limit")?);
+        }
+        world.progressive = Some(progressive);
+    }
+    world.checkpoint = opt
+        .checkpoint
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned());
+    world.sample_map = opt
+        .sample_map
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned());
+    if let Some(max_depth) = opt.max_depth {
+        world.max_depth = max_depth;
+    }
+    if let Some(rr_depth) = opt.rr_depth {
+        world.rr_depth = rr_depth;
+    }
+    if let Some(name) = &opt.integrator {
+        world.integrator = integrators::from_name(name).unwrap();
+    }
+    if let Some(name) = &opt.sampler {
+        world.sampler = samplers::from_name(name).unwrap();
+    }
+    if opt.seed.is_some() {
+        world.seed = opt.seed;
+    }
+    if let Some(name) = &opt.tone_map {
+        // Keeps the scene's white point if it already has one
+        let white = match world.tone_map {
+            ToneMap::ExtendedReinhard { white } => white,
+            _ => ToneMap::DEFAULT_WHITE,
+        };
+        world.tone_map = ToneMap::from_name(name, white).unwrap();
+    }
+    // Applies to the scene's tone map too, as long as it's one that takes it
+    if let Some(white_point) = opt.white_point {
+        if white_point <= 0. {
+            return Err("White point must be greater than 0".to_string());
+        }
+        match &mut world.tone_map {
+            ToneMap::ExtendedReinhard { white } => *white = white_point,
+            _ => {
+                return Err(
+                    "--white-point only applies to the extended_reinhard tone map".to_string(),
+                )
+            }
+        }
+    }
+    if let Some(exposure) = opt.exposure {
+        world.exposure = exposure;
+    }
+    if let Some(threads) = opt.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| format!("Failed to start {} threads: {}", threads, e))?;
+    }
+    Ok(())
+}
+
+fn seconds(secs: f64, what: &str) -> Result<Duration, String> {
+    if secs.is_finite() && secs >= 0. {
+        Ok(Duration::from_secs_f64(secs))
+    } else {
+        Err(format!("{} can't be negative", what))
+    }
+}
+
+fn default_scene() -> World {
+    let mut world = World::builder().build();
+
     world.add(Sphere::new(
         Vector3::new(0., -1000.0, 0.0),
         1000.0,
@@ -47,12 +352,11 @@ fn main() {
         Lambertian::new(SolidColor::new((0.1, 0.2, 0.7).into())),
     ));
 
-    world.run(filename);
-    eprintln!("Done");
+    world
 }
 
 fn random_scene() -> World {
-    let mut world = World::new();
+    let mut world = World::builder().build();
 
     let ground = Lambertian::new(SolidColor::new((0.5, 0.5, 0.5).into()));
     world.add(Sphere::new(Vector3::new(0.0, -1000.0, 0.0), 1000.0, ground));
@@ -76,7 +380,7 @@ fn random_scene() -> World {
                         Lambertian::new(SolidColor::new(albedo)),
                     ));
                 } else if choose_mat < 0.95 {
-                    let cvec = Vector3::random(0.5, 1.0);
+                    let cvec = Vector3::random(0.5, 1.0, &mut rng);
                     let albedo = RGB::new(cvec.x, cvec.y, cvec.z);
                     let fuzz = rng.gen_range(0.0, 0.5);
                     world.add(Sphere::new(center, 0.2, Metal::new(albedo, fuzz)));
@@ -1,26 +1,96 @@
 pub mod dielectric;
+pub mod diffuse_light;
 pub mod lambertian;
 pub mod metal;
 
 use crate::objects::HitResult;
 use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
 
 use rgb::RGB;
+use std::sync::Arc;
 
 pub struct MaterialResult {
+    // For sampled scattering this is the BSDF value for `scattered`,
+    // for specular scattering the fraction of light carried along it
     pub attenuation: RGB<f64>,
     pub scattered: Ray,
+    // Density `scattered` was sampled with, or None for specular (delta) scattering
+    pub pdf: Option<f64>,
 }
 
 pub trait Material: Sync + Send {
-    fn scatter(&self, ray_in: &Ray, hr: &HitResult) -> Option<MaterialResult>;
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hr: &HitResult,
+        sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult>;
+
+    fn emitted(&self, _u: f64, _v: f64, _point: Vector3) -> RGB<f64> {
+        RGB::new(0., 0., 0.)
+    }
+
+    // BSDF value for light leaving along `dir`. Zero for purely specular materials.
+    fn bsdf(&self, _ray_in: &Ray, _hr: &HitResult, _dir: &Vector3) -> RGB<f64> {
+        RGB::new(0., 0., 0.)
+    }
+
+    // Density with which `scatter` picks `dir`. Zero for purely specular materials.
+    fn pdf(&self, _ray_in: &Ray, _hr: &HitResult, _dir: &Vector3) -> f64 {
+        0.
+    }
+
+    // Base color at the hit, for debug output
+    fn albedo(&self, _hr: &HitResult) -> RGB<f64> {
+        RGB::new(0., 0., 0.)
+    }
+}
+
+// Lets one material be shared between several objects
+impl<M: Material + ?Sized> Material for Arc<M> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hr: &HitResult,
+        sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult> {
+        (**self).scatter(ray_in, hr, sampler)
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: Vector3) -> RGB<f64> {
+        (**self).emitted(u, v, point)
+    }
+
+    fn bsdf(&self, ray_in: &Ray, hr: &HitResult, dir: &Vector3) -> RGB<f64> {
+        (**self).bsdf(ray_in, hr, dir)
+    }
+
+    fn pdf(&self, ray_in: &Ray, hr: &HitResult, dir: &Vector3) -> f64 {
+        (**self).pdf(ray_in, hr, dir)
+    }
+
+    fn albedo(&self, hr: &HitResult) -> RGB<f64> {
+        (**self).albedo(hr)
+    }
 }
 
 impl MaterialResult {
+    // Specular scattering
     pub fn new(attenuation: RGB<f64>, scattered: Ray) -> MaterialResult {
         MaterialResult {
             attenuation,
             scattered,
+            pdf: None,
+        }
+    }
+
+    pub fn sampled(bsdf: RGB<f64>, scattered: Ray, pdf: f64) -> MaterialResult {
+        MaterialResult {
+            attenuation: bsdf,
+            scattered,
+            pdf: Some(pdf),
         }
     }
 }
@@ -1,9 +1,9 @@
 use crate::materials::{Material, MaterialResult};
 use crate::objects::HitResult;
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::vec3::Vector3;
 
-use rand::Rng;
 use rgb::RGB;
 
 pub struct Dielectric {
@@ -18,7 +18,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hr: &HitResult) -> Option<MaterialResult> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hr: &HitResult,
+        sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult> {
         let etai_over_etat = if hr.face {
             1.0 / self.ref_idx
         } else {
@@ -35,8 +40,7 @@ impl Material for Dielectric {
                 Ray::new(hr.hit_point, reflected),
             ))
         } else {
-            let mut rng = rand::thread_rng();
-            if rng.gen::<f64>() < schlick(cos_theta, self.ref_idx) {
+            if sampler.get_1d() < schlick(cos_theta, self.ref_idx) {
                 Some(MaterialResult::new(
                     RGB::new(1.0, 1.0, 1.0),
                     Ray::new(hr.hit_point, reflected),
@@ -50,6 +54,10 @@ impl Material for Dielectric {
             }
         }
     }
+
+    fn albedo(&self, _hr: &HitResult) -> RGB<f64> {
+        RGB::new(1., 1., 1.)
+    }
 }
 
 fn schlick(cosine: f64, ref_idx: f64) -> f64 {
@@ -0,0 +1,39 @@
+use crate::materials::{Material, MaterialResult};
+use crate::objects::HitResult;
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::textures::Texture;
+use crate::vec3::Vector3;
+
+use rgb::RGB;
+
+pub struct DiffuseLight {
+    emit: Box<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: impl Texture + 'static) -> DiffuseLight {
+        DiffuseLight {
+            emit: Box::new(emit),
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        _hr: &HitResult,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult> {
+        None
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: Vector3) -> RGB<f64> {
+        self.emit.value(u, v, point)
+    }
+
+    fn albedo(&self, hr: &HitResult) -> RGB<f64> {
+        self.emit.value(hr.u, hr.v, hr.hit_point)
+    }
+}
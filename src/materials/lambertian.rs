@@ -1,9 +1,14 @@
 use crate::materials::{Material, MaterialResult};
 use crate::objects::HitResult;
+use crate::pdf::{CosinePdf, Pdf};
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::textures::Texture;
 use crate::vec3::Vector3;
 
+use rgb::RGB;
+use std::f64::consts::PI;
+
 pub struct Lambertian {
     texture: Box<dyn Texture>,
 }
@@ -17,12 +22,38 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, hr: &HitResult) -> Option<MaterialResult> {
-        let attenuation = self.texture.value(hr.u, hr.v, hr.hit_point);
-        let target = hr.normal + Vector3::random_unit_vec();
-        Some(MaterialResult::new(
-            attenuation,
-            Ray::new(hr.hit_point, target),
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hr: &HitResult,
+        sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult> {
+        let pdf = CosinePdf::new(&hr.normal);
+        let dir = pdf.generate(sampler);
+        let pdf_value = pdf.value(&dir);
+        if pdf_value <= 0. {
+            return None;
+        }
+
+        Some(MaterialResult::sampled(
+            self.bsdf(ray_in, hr, &dir),
+            Ray::new(hr.hit_point, dir),
+            pdf_value,
         ))
     }
+
+    fn bsdf(&self, _ray_in: &Ray, hr: &HitResult, dir: &Vector3) -> RGB<f64> {
+        if dir.dot(&hr.normal) <= 0. {
+            return RGB::new(0., 0., 0.);
+        }
+        self.texture.value(hr.u, hr.v, hr.hit_point) * (1. / PI)
+    }
+
+    fn pdf(&self, _ray_in: &Ray, hr: &HitResult, dir: &Vector3) -> f64 {
+        CosinePdf::new(&hr.normal).value(dir)
+    }
+
+    fn albedo(&self, hr: &HitResult) -> RGB<f64> {
+        self.texture.value(hr.u, hr.v, hr.hit_point)
+    }
 }
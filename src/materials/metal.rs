@@ -1,6 +1,7 @@
 use crate::materials::{Material, MaterialResult};
 use crate::objects::HitResult;
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::vec3::Vector3;
 
 use rgb::RGB;
@@ -18,12 +19,17 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hr: &HitResult) -> Option<MaterialResult> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hr: &HitResult,
+        sampler: &mut dyn Sampler,
+    ) -> Option<MaterialResult> {
         let attenuation = RGB::new(self.color.r, self.color.g, self.color.b);
         let reflected = ray_in.dir.unit_vec().reflect(&hr.normal);
         let scattered = Ray::new(
             hr.hit_point,
-            reflected + Vector3::random_in_unit_sphere() * self.fuzz,
+            reflected + Vector3::random_in_unit_sphere(sampler) * self.fuzz,
         );
         if scattered.dir.dot(&hr.normal) > 0. {
             Some(MaterialResult::new(attenuation, scattered))
@@ -31,4 +37,8 @@ impl Material for Metal {
             None
         }
     }
+
+    fn albedo(&self, _hr: &HitResult) -> RGB<f64> {
+        self.color
+    }
 }
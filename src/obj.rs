@@ -0,0 +1,628 @@
+use crate::materials::{
+    dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian, metal::Metal,
+    Material,
+};
+use crate::objects::mesh::{MeshFace, TriangleMesh};
+use crate::textures::{color::SolidColor, image::ImageTexture};
+use crate::vec3::Vector3;
+use crate::world::World;
+
+use image::ImageError;
+use rgb::RGB;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, io::Error),
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    Texture(PathBuf, ImageError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::Texture(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// Material settings read from an .mtl file
+struct MtlDesc {
+    kd: RGB<f64>,
+    map_kd: Option<PathBuf>,
+    ks: RGB<f64>,
+    ns: f64,
+    ni: f64,
+    d: f64,
+    ke: RGB<f64>,
+}
+
+impl Default for MtlDesc {
+    fn default() -> Self {
+        MtlDesc {
+            kd: RGB::new(0.8, 0.8, 0.8),
+            map_kd: None,
+            ks: RGB::new(0., 0., 0.),
+            ns: 0.,
+            ni: 1.5,
+            d: 1.,
+            ke: RGB::new(0., 0., 0.),
+        }
+    }
+}
+
+// Corner of a face: indices into the file's position, uv and normal lists
+#[derive(Copy, Clone)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// Faces sharing one material
+struct Group {
+    material: Option<String>,
+    faces: Vec<[Corner; 3]>,
+}
+
+// Meshes read from an .obj file
+pub struct ObjMeshes {
+    pub meshes: Vec<TriangleMesh>,
+    // Meshes with an emissive (`Ke`) material, to be sampled directly as lights
+    pub lights: Vec<TriangleMesh>,
+    // Problems that were worked around, such as a missing .mtl file
+    pub warnings: Vec<String>,
+}
+
+// Loads an .obj file and adds one mesh per material to the world, returning any warnings
+pub fn load(path: impl AsRef<Path>, world: &mut World) -> Result<Vec<String>, ObjError> {
+    let obj = load_meshes(path)?;
+    for mesh in obj.meshes {
+        world.add(mesh);
+    }
+    for light in obj.lights {
+        world.add_light(light);
+    }
+    Ok(obj.warnings)
+}
+
+// Loads an .obj file into one mesh per material, along with the .mtl files it references.
+// Materials are mapped as follows:
+//  - `d` < 1 (or `Tr` > 0): Dielectric with `Ni` as the refractive index
+//  - non-black `Ke`: DiffuseLight, with the mesh returned in `lights`
+//  - `Ks` brighter than `Kd` and no `map_Kd`: Metal, with `Ns` controlling the fuzz
+//  - otherwise: Lambertian textured with `map_Kd`, or colored with `Kd`
+// Faces whose material can't be found, because its .mtl file is missing or doesn't
+// define it, get the default material and a warning.
+pub fn load_meshes(path: impl AsRef<Path>) -> Result<ObjMeshes, ObjError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut current: Option<usize> = None;
+    let mut mtls = HashMap::new();
+    let mut warnings = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let parse_err = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: i + 1,
+            message,
+        };
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vector(&args, 3).map_err(parse_err)?),
+            "vn" => normals.push(parse_vector(&args, 3).map_err(parse_err)?),
+            "vt" => {
+                let uv = parse_vector(&args, 1).map_err(parse_err)?;
+                uvs.push((uv.x, uv.y));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_err("face needs at least 3 vertices".to_string()));
+                }
+                let corners = args
+                    .iter()
+                    .map(|arg| parse_corner(arg, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(parse_err)?;
+
+                let group = match current {
+                    Some(group) => group,
+                    None => {
+                        groups.push(Group {
+                            material: None,
+                            faces: Vec::new(),
+                        });
+                        current = Some(groups.len() - 1);
+                        groups.len() - 1
+                    }
+                };
+                // Polygons are split into a fan around the first vertex
+                for j in 1..corners.len() - 1 {
+                    groups[group]
+                        .faces
+                        .push([corners[0], corners[j], corners[j + 1]]);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                current = match groups
+                    .iter()
+                    .position(|g| g.material.as_deref() == Some(&name))
+                {
+                    Some(group) => Some(group),
+                    None => {
+                        groups.push(Group {
+                            material: Some(name),
+                            faces: Vec::new(),
+                        });
+                        Some(groups.len() - 1)
+                    }
+                };
+            }
+            "mtllib" => {
+                let file = base_dir.join(args.join(" "));
+                match load_mtl(&file, &mut mtls) {
+                    Err(ObjError::Io(_, e)) if e.kind() == io::ErrorKind::NotFound => {
+                        warnings.push(format!("{}: {}", file.display(), e))
+                    }
+                    result => result?,
+                }
+            }
+            // Object and group names, smoothing groups etc. don't affect rendering
+            _ => {}
+        }
+    }
+
+    let mut meshes = Vec::new();
+    let mut lights = Vec::new();
+    for group in groups.into_iter().filter(|g| !g.faces.is_empty()) {
+        let desc = group.material.as_ref().and_then(|name| mtls.get(name));
+        let mat = match &group.material {
+            Some(name) => match desc {
+                Some(desc) => material(desc)?,
+                None => {
+                    warnings.push(format!(
+                        "{}: unknown material \"{}\", using the default",
+                        path.display(),
+                        name
+                    ));
+                    default_material()
+                }
+            },
+            None => default_material(),
+        };
+        let mesh = build_mesh(&group, &positions, &uvs, &normals, mat);
+        if desc.is_some_and(is_emissive) {
+            lights.push(mesh);
+        } else {
+            meshes.push(mesh);
+        }
+    }
+    Ok(ObjMeshes {
+        meshes,
+        lights,
+        warnings,
+    })
+}
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(SolidColor::new(MtlDesc::default().kd)))
+}
+
+// Copies just the vertices a group uses into the mesh's own buffers
+fn build_mesh(
+    group: &Group,
+    positions: &[Vector3],
+    uvs: &[(f64, f64)],
+    normals: &[Vector3],
+    mat: Arc<dyn Material>,
+) -> TriangleMesh {
+    let mut position_map = HashMap::new();
+    let mut uv_map = HashMap::new();
+    let mut normal_map = HashMap::new();
+    let mut mesh_positions = Vec::new();
+    let mut mesh_uvs = Vec::new();
+    let mut mesh_normals = Vec::new();
+
+    fn remap<T: Copy>(
+        idx: usize,
+        map: &mut HashMap<usize, usize>,
+        src: &[T],
+        dst: &mut Vec<T>,
+    ) -> usize {
+        *map.entry(idx).or_insert_with(|| {
+            dst.push(src[idx]);
+            dst.len() - 1
+        })
+    }
+
+    let faces = group
+        .faces
+        .iter()
+        .map(|corners| {
+            let mut face = MeshFace {
+                positions: [0; 3],
+                normals: None,
+                uvs: None,
+            };
+            for (k, corner) in corners.iter().enumerate() {
+                face.positions[k] = remap(
+                    corner.position,
+                    &mut position_map,
+                    positions,
+                    &mut mesh_positions,
+                );
+            }
+            // Only use normals and uvs if all three corners have them
+            if corners.iter().all(|c| c.normal.is_some()) {
+                let mut idx = [0; 3];
+                for (k, corner) in corners.iter().enumerate() {
+                    idx[k] = remap(
+                        corner.normal.unwrap(),
+                        &mut normal_map,
+                        normals,
+                        &mut mesh_normals,
+                    );
+                }
+                face.normals = Some(idx);
+            }
+            if corners.iter().all(|c| c.uv.is_some()) {
+                let mut idx = [0; 3];
+                for (k, corner) in corners.iter().enumerate() {
+                    idx[k] = remap(corner.uv.unwrap(), &mut uv_map, uvs, &mut mesh_uvs);
+                }
+                face.uvs = Some(idx);
+            }
+            face
+        })
+        .collect();
+
+    TriangleMesh::new(mesh_positions, mesh_normals, mesh_uvs, faces, mat)
+}
+
+fn parse_vector(args: &[&str], required: usize) -> Result<Vector3, String> {
+    if args.len() < required {
+        return Err(format!(
+            "expected {} numbers, found {}",
+            required,
+            args.len()
+        ));
+    }
+    let mut v = [0.; 3];
+    for (k, arg) in args.iter().take(3).enumerate() {
+        v[k] = arg
+            .parse()
+            .map_err(|_| format!("invalid number \"{}\"", arg))?;
+    }
+    Ok(Vector3::new(v[0], v[1], v[2]))
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`. Indices start at 1; negative indices
+// count back from the last element read so far.
+fn parse_corner(arg: &str, positions: usize, uvs: usize, normals: usize) -> Result<Corner, String> {
+    let mut parts = arg.split('/');
+    let index = |part: Option<&str>, len: usize, what: &str| -> Result<Option<usize>, String> {
+        let part = match part {
+            Some(p) if !p.is_empty() => p,
+            _ => return Ok(None),
+        };
+        let idx: i64 = part
+            .parse()
+            .map_err(|_| format!("invalid {} index \"{}\"", what, part))?;
+        let resolved = if idx > 0 { idx - 1 } else { len as i64 + idx };
+        if idx == 0 || resolved < 0 || resolved >= len as i64 {
+            return Err(format!("{} index {} is out of range", what, idx));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = index(parts.next(), positions, "vertex")?
+        .ok_or_else(|| format!("missing vertex index in \"{}\"", arg))?;
+    let uv = index(parts.next(), uvs, "texture coordinate")?;
+    let normal = index(parts.next(), normals, "normal")?;
+    Ok(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+fn load_mtl(path: &Path, mtls: &mut HashMap<String, MtlDesc>) -> Result<(), ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut current: Option<String> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let parse_err = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: i + 1,
+            message,
+        };
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            let name = args.join(" ");
+            mtls.insert(name.clone(), MtlDesc::default());
+            current = Some(name);
+            continue;
+        }
+        let desc = match current.as_ref().and_then(|name| mtls.get_mut(name)) {
+            Some(desc) => desc,
+            None => return Err(parse_err(format!("\"{}\" before newmtl", keyword))),
+        };
+        let scalar = || -> Result<f64, ObjError> {
+            let v = parse_vector(&args, 1).map_err(parse_err)?;
+            Ok(v.x)
+        };
+        let color = || -> Result<RGB<f64>, ObjError> {
+            let v = parse_vector(&args, 3).map_err(parse_err)?;
+            Ok(RGB::new(v.x, v.y, v.z))
+        };
+
+        match keyword {
+            "Kd" => desc.kd = color()?,
+            "Ks" => desc.ks = color()?,
+            "Ke" => desc.ke = color()?,
+            "Ns" => desc.ns = scalar()?,
+            "Ni" => {
+                desc.ni = scalar()?;
+                if desc.ni <= 0. {
+                    return Err(parse_err("Ni must be greater than 0".to_string()));
+                }
+            }
+            "d" => desc.d = scalar()?,
+            "Tr" => desc.d = 1. - scalar()?,
+            // Texture options come before the file name
+            "map_Kd" => match args.last() {
+                Some(file) => desc.map_kd = Some(base_dir.join(file)),
+                None => return Err(parse_err("missing file name".to_string())),
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn max(c: RGB<f64>) -> f64 {
+    c.r.max(c.g).max(c.b)
+}
+
+// Whether `material` turns this into a DiffuseLight
+fn is_emissive(desc: &MtlDesc) -> bool {
+    desc.d >= 1. && max(desc.ke) > 0.
+}
+
+fn material(desc: &MtlDesc) -> Result<Arc<dyn Material>, ObjError> {
+    if desc.d < 1. {
+        return Ok(Arc::new(Dielectric::new(desc.ni)));
+    }
+    if is_emissive(desc) {
+        return Ok(Arc::new(DiffuseLight::new(SolidColor::new(desc.ke))));
+    }
+    if let Some(path) = &desc.map_kd {
+        let texture = ImageTexture::open(path).map_err(|e| ObjError::Texture(path.clone(), e))?;
+        return Ok(Arc::new(Lambertian::new(texture)));
+    }
+    if max(desc.ks) > max(desc.kd) {
+        // Phong exponent to roughness
+        let fuzz = (2. / (desc.ns.max(0.) + 2.)).sqrt();
+        return Ok(Arc::new(Metal::new(desc.ks, fuzz)));
+    }
+    Ok(Arc::new(Lambertian::new(SolidColor::new(desc.kd))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{HitResult, Hittable};
+    use crate::ray::Ray;
+
+    // Writes `files` to a fresh directory named after the test and returns its path
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in files {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+        dir
+    }
+
+    // Whether a ray straight down the z axis through (x, y) hits `obj`
+    fn hits(obj: &dyn Hittable, x: f64, y: f64) -> bool {
+        let ray = Ray::new(Vector3::new(x, y, 1.), Vector3::new(0., 0., -1.));
+        obj.intersect(&ray, 0.001).is_some()
+    }
+
+    #[test]
+    fn parses_corner_formats() {
+        let corner = parse_corner("2", 3, 2, 3).unwrap();
+        assert_eq!((corner.position, corner.uv, corner.normal), (1, None, None));
+        let corner = parse_corner("2/1/3", 3, 2, 3).unwrap();
+        assert_eq!(
+            (corner.position, corner.uv, corner.normal),
+            (1, Some(0), Some(2))
+        );
+        let corner = parse_corner("3//2", 3, 2, 3).unwrap();
+        assert_eq!(
+            (corner.position, corner.uv, corner.normal),
+            (2, None, Some(1))
+        );
+        // Negative indices count back from the end
+        let corner = parse_corner("-1/-2/-3", 3, 2, 3).unwrap();
+        assert_eq!(
+            (corner.position, corner.uv, corner.normal),
+            (2, Some(0), Some(0))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_indices() {
+        for arg in &["0", "4", "-4", "1/3", "1//0", "x"] {
+            assert!(parse_corner(arg, 3, 2, 3).is_err(), "{} was accepted", arg);
+        }
+
+        let dir = write_files(
+            "obj-short-face",
+            &[("a.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2\n")],
+        );
+        match load_meshes(dir.join("a.obj")) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 4),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("a face with 2 vertices was accepted"),
+        }
+    }
+
+    #[test]
+    fn fans_polygons() {
+        // A unit square, and a hexagon of radius 1 around (3, 0)
+        let mut obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n".to_string();
+        for k in 0..6 {
+            let angle = k as f64 * std::f64::consts::PI / 3.;
+            obj += &format!("v {} {} 0\n", 3. + angle.cos(), angle.sin());
+        }
+        obj += "f 5 6 7 8 9 10\n";
+        let dir = write_files("obj-fan", &[("a.obj", &obj)]);
+
+        let meshes = load_meshes(dir.join("a.obj")).unwrap().meshes;
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        for i in 0..9 {
+            for j in 0..9 {
+                let (x, y) = (0.1 * (i + 1) as f64, 0.1 * (j + 1) as f64);
+                assert!(hits(mesh, x, y), "square missed at ({}, {})", x, y);
+                // Inside the hexagon's inscribed circle
+                let (x, y) = (2.2 + 0.2 * i as f64, -0.8 + 0.2 * j as f64);
+                if (x - 3.).hypot(y) < 0.85 {
+                    assert!(hits(mesh, x, y), "hexagon missed at ({}, {})", x, y);
+                }
+            }
+        }
+        assert!(!hits(mesh, 1.5, 0.5));
+        assert!(!hits(mesh, 3.9, 0.9));
+    }
+
+    #[test]
+    fn groups_faces_by_material() {
+        let obj = "mtllib m.mtl\n\
+                   v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                   v 2 0 0\nv 3 0 0\nv 2 1 0\n\
+                   v 4 0 0\nv 5 0 0\nv 4 1 0\n\
+                   v 6 0 0\nv 7 0 0\nv 6 1 0\n\
+                   f 1 2 3\n\
+                   usemtl lamp\nf 4 5 6\n\
+                   usemtl paint\nf 7 8 9\n\
+                   usemtl lamp\nf 10 11 12\n";
+        let mtl = "newmtl lamp\nKe 4 4 4\nnewmtl paint\nKd 0.5 0.1 0.1\n";
+        let dir = write_files("obj-groups", &[("a.obj", obj), ("m.mtl", mtl)]);
+
+        let obj = load_meshes(dir.join("a.obj")).unwrap();
+        assert!(obj.warnings.is_empty());
+        // Faces before any usemtl, and the faces using "paint"
+        assert_eq!(obj.meshes.len(), 2);
+        assert_eq!(obj.lights.len(), 1);
+        let lamp = &obj.lights[0];
+        assert!(hits(lamp, 2.2, 0.2) && hits(lamp, 6.2, 0.2));
+        assert!(!hits(lamp, 0.2, 0.2) && !hits(lamp, 4.2, 0.2));
+    }
+
+    #[test]
+    fn maps_mtl_materials() {
+        let mtl = "newmtl matte\nKd 0.2 0.3 0.4\n\
+                   newmtl shiny\nKd 0.1 0.1 0.1\nKs 0.9 0.8 0.7\nNs 100\n\
+                   newmtl glass\nd 0.2\nNi 1.3\n\
+                   newmtl lamp\nKe 5 6 7\n";
+        let dir = write_files("obj-mtl", &[("m.mtl", mtl)]);
+        let mut mtls = HashMap::new();
+        load_mtl(&dir.join("m.mtl"), &mut mtls).unwrap();
+        assert_eq!(mtls["glass"].ni, 1.3);
+
+        let normal = Vector3::new(0., 0., 1.);
+        let ray = Ray::new(Vector3::new(0., 0., 1.), -normal);
+        let probe = |name: &str| {
+            let mat = material(&mtls[name]).unwrap();
+            let hr = HitResult::new(1., Vector3::new(0., 0., 0.), 0., 0., normal, true, mat);
+            let mat = &hr.mat;
+            (
+                mat.albedo(&hr),
+                mat.pdf(&ray, &hr, &normal),
+                mat.emitted(0., 0., hr.hit_point),
+            )
+        };
+        let black = RGB::new(0., 0., 0.);
+
+        // Lambertian: diffuse, colored by Kd
+        let (albedo, pdf, emitted) = probe("matte");
+        assert_eq!(albedo, RGB::new(0.2, 0.3, 0.4));
+        assert!(pdf > 0.);
+        assert_eq!(emitted, black);
+        // Metal: specular, colored by Ks
+        let (albedo, pdf, _) = probe("shiny");
+        assert_eq!(albedo, RGB::new(0.9, 0.8, 0.7));
+        assert_eq!(pdf, 0.);
+        // Dielectric: specular and colorless
+        let (albedo, pdf, _) = probe("glass");
+        assert_eq!(albedo, RGB::new(1., 1., 1.));
+        assert_eq!(pdf, 0.);
+        // DiffuseLight: emits Ke
+        let (_, _, emitted) = probe("lamp");
+        assert_eq!(emitted, RGB::new(5., 6., 7.));
+        assert!(is_emissive(&mtls["lamp"]) && !is_emissive(&mtls["matte"]));
+    }
+
+    #[test]
+    fn rejects_non_positive_ni() {
+        let dir = write_files("obj-ni", &[("m.mtl", "newmtl glass\nd 0.5\nNi 0\n")]);
+        match load_mtl(&dir.join("m.mtl"), &mut HashMap::new()) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 3),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(()) => panic!("Ni 0 was accepted"),
+        }
+    }
+
+    #[test]
+    fn missing_mtl_falls_back_to_default() {
+        let obj = "mtllib no such file.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl paint\nf 1 2 3\n";
+        let dir = write_files("obj-missing-mtl", &[("a.obj", obj)]);
+
+        let obj = load_meshes(dir.join("a.obj")).unwrap();
+        assert_eq!(obj.meshes.len(), 1);
+        assert_eq!(obj.warnings.len(), 2);
+        assert!(obj.warnings[0].contains("no such file.mtl"));
+        assert!(obj.warnings[1].contains("paint"));
+    }
+}
@@ -1,13 +1,36 @@
+pub mod aabb;
+pub mod bvh;
+pub mod cuboid;
+pub mod instance;
+pub mod mesh;
+pub mod rect;
 pub mod sphere;
+pub mod triangle;
 
 use crate::materials::Material;
+use crate::objects::aabb::Aabb;
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::vec3::Vector3;
 
 use std::sync::Arc;
 
 pub trait Hittable: Sync + Send {
     fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult>;
+
+    // None if the object is unbounded
+    fn aabb(&self) -> Option<Aabb>;
+
+    // Density, over solid angle at `origin`, with which `sample_direction` picks `dir`.
+    // Objects that can't be sampled return 0 and are only found by chance.
+    fn pdf_value(&self, _origin: &Vector3, _dir: &Vector3) -> f64 {
+        0.
+    }
+
+    // Random direction from `origin` towards the object, used to sample lights
+    fn sample_direction(&self, _origin: &Vector3, _sampler: &mut dyn Sampler) -> Vector3 {
+        Vector3::new(1., 0., 0.)
+    }
 }
 
 pub struct HitResult {
@@ -59,6 +82,31 @@ impl HitList {
     }
 }
 
+// Lets one object be placed several times, e.g. through an Instance
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        (**self).intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        (**self).aabb()
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        (**self).pdf_value(origin, dir)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        (**self).sample_direction(origin, sampler)
+    }
+}
+
+impl Default for HitList {
+    fn default() -> Self {
+        HitList::new()
+    }
+}
+
 impl Hittable for HitList {
     fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
         self.objects
@@ -66,4 +114,32 @@ impl Hittable for HitList {
             .filter_map(|obj| obj.intersect(ray, min))
             .min_by(|hr1, hr2| hr1.dist.partial_cmp(&hr2.dist).unwrap())
     }
+
+    fn aabb(&self) -> Option<Aabb> {
+        let mut objects = self.objects.iter();
+        let first = objects.next()?.aabb()?;
+        objects.try_fold(first, |acc, obj| Some(acc.surrounding(&obj.aabb()?)))
+    }
+
+    // Picks one of the objects uniformly
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.;
+        }
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|obj| obj.pdf_value(origin, dir))
+            .sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        if self.objects.is_empty() {
+            return Vector3::new(1., 0., 0.);
+        }
+        let n = self.objects.len();
+        let i = ((sampler.get_1d() * n as f64) as usize).min(n - 1);
+        self.objects[i].sample_direction(origin, sampler)
+    }
 }
@@ -0,0 +1,125 @@
+use crate::objects::aabb::Aabb;
+use crate::objects::{HitList, HitResult, Hittable};
+use crate::ray::Ray;
+
+pub struct Bvh {
+    root: Option<BvhNode>,
+    // Objects without a bounding box can't be placed in the tree,
+    // so they are tested against every ray
+    unbounded: Vec<Box<dyn Hittable>>,
+}
+
+struct BvhNode {
+    bbox: Aabb,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    Leaf(Box<dyn Hittable>),
+    Branch(Box<BvhNode>, Box<BvhNode>),
+}
+
+impl Bvh {
+    pub fn new(list: HitList) -> Bvh {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+        for obj in list.objects {
+            match obj.aabb() {
+                Some(bbox) => bounded.push((bbox, obj)),
+                None => unbounded.push(obj),
+            }
+        }
+
+        let root = if bounded.is_empty() {
+            None
+        } else {
+            Some(BvhNode::build(bounded))
+        };
+        Bvh { root, unbounded }
+    }
+}
+
+impl BvhNode {
+    fn build(mut objects: Vec<(Aabb, Box<dyn Hittable>)>) -> BvhNode {
+        if objects.len() == 1 {
+            let (bbox, obj) = objects.pop().unwrap();
+            return BvhNode {
+                bbox,
+                kind: NodeKind::Leaf(obj),
+            };
+        }
+
+        // Split at the median centroid along the longest axis of the centroids' extent
+        let first = objects[0].0.centroid();
+        let centroids = objects
+            .iter()
+            .fold(Aabb::new(first, first), |acc, (bbox, _)| {
+                let c = bbox.centroid();
+                acc.surrounding(&Aabb::new(c, c))
+            });
+        let axis = centroids.longest_axis();
+
+        let mid = objects.len() / 2;
+        objects.select_nth_unstable_by(mid, |(a, _), (b, _)| {
+            a.centroid()[axis]
+                .partial_cmp(&b.centroid()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let right = objects.split_off(mid);
+
+        let left = BvhNode::build(objects);
+        let right = BvhNode::build(right);
+        BvhNode {
+            bbox: left.bbox.surrounding(&right.bbox),
+            kind: NodeKind::Branch(Box::new(left), Box::new(right)),
+        }
+    }
+
+    fn intersect(&self, ray: &Ray, min: f64, max: f64) -> Option<(HitResult, &dyn Hittable)> {
+        if !self.bbox.hit(ray, min, max) {
+            return None;
+        }
+
+        match &self.kind {
+            NodeKind::Leaf(obj) => obj
+                .intersect(ray, min)
+                .filter(|hr| hr.dist < max)
+                .map(|hr| (hr, obj.as_ref())),
+            NodeKind::Branch(left, right) => {
+                let hit_left = left.intersect(ray, min, max);
+                let max = hit_left.as_ref().map_or(max, |(hr, _)| hr.dist);
+                right.intersect(ray, min, max).or(hit_left)
+            }
+        }
+    }
+}
+
+impl Bvh {
+    // Closest hit, along with the object that was hit
+    pub fn intersect_object(&self, ray: &Ray, min: f64) -> Option<(HitResult, &dyn Hittable)> {
+        let closest = self
+            .unbounded
+            .iter()
+            .filter_map(|obj| obj.intersect(ray, min).map(|hr| (hr, obj.as_ref())))
+            .min_by(|(hr1, _), (hr2, _)| hr1.dist.partial_cmp(&hr2.dist).unwrap());
+        let max = closest.as_ref().map_or(f64::INFINITY, |(hr, _)| hr.dist);
+
+        match &self.root {
+            Some(root) => root.intersect(ray, min, max).or(closest),
+            None => closest,
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.intersect_object(ray, min).map(|(hr, _)| hr)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.root.as_ref().map(|root| root.bbox)
+    }
+}
@@ -0,0 +1,53 @@
+use crate::materials::Material;
+use crate::objects::aabb::Aabb;
+use crate::objects::rect::{XyRect, XzRect, YzRect};
+use crate::objects::{HitList, HitResult, Hittable};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use std::sync::Arc;
+
+// Axis-aligned box made of six rectangles
+pub struct Cuboid {
+    sides: HitList,
+}
+
+impl Cuboid {
+    pub fn new(p0: Vector3, p1: Vector3, mat: impl Material + 'static) -> Cuboid {
+        let min = p0.min(&p1);
+        let max = p0.max(&p1);
+        let mat: Arc<dyn Material> = Arc::new(mat);
+
+        let mut sides = HitList::new();
+        for &z in &[min.z, max.z] {
+            sides.add(XyRect::new(min.x, max.x, min.y, max.y, z, Arc::clone(&mat)));
+        }
+        for &y in &[min.y, max.y] {
+            sides.add(XzRect::new(min.x, max.x, min.z, max.z, y, Arc::clone(&mat)));
+        }
+        for &x in &[min.x, max.x] {
+            sides.add(YzRect::new(min.y, max.y, min.z, max.z, x, Arc::clone(&mat)));
+        }
+
+        Cuboid { sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.sides.intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.sides.aabb()
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        self.sides.pdf_value(origin, dir)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        self.sides.sample_direction(origin, sampler)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::objects::aabb::Aabb;
+use crate::objects::{HitResult, Hittable};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::transform::Transform;
+use crate::vec3::Vector3;
+
+use std::sync::Arc;
+
+// Places a shared object in the world with a transform
+pub struct Instance {
+    pub object: Arc<dyn Hittable>,
+    pub transform: Transform,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        Instance { object, transform }
+    }
+}
+
+impl Hittable for Instance {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        // The direction isn't normalized, so distances along the ray are the same in both spaces
+        let local = Ray::new(
+            self.transform.inverse.point(ray.origin),
+            self.transform.inverse.vector(ray.dir),
+        );
+        let mut hr = self.object.intersect(&local, min)?;
+
+        hr.hit_point = ray.at(hr.dist);
+        hr.normal = self.transform.normal(hr.normal).unit_vec();
+        Some(hr)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        let bbox = self.object.aabb()?;
+        let mut min = Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+        for i in 0..8 {
+            let corner = Vector3::new(
+                if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                if i & 4 == 0 { bbox.min.z } else { bbox.max.z },
+            );
+            let p = self.transform.point(corner);
+            min = min.min(&p);
+            max = max.max(&p);
+        }
+        Some(Aabb::new(min, max))
+    }
+
+    // The object's PDF is over directions in its own space, which a transform that
+    // scales or shears stretches. It's converted to an area density at the point the
+    // ray hits, scaled to world space, then converted back to solid angle.
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        let local = Ray::new(
+            self.transform.inverse.point(*origin),
+            self.transform.inverse.vector(*dir),
+        );
+        let pdf = self.object.pdf_value(&local.origin, &local.dir);
+        if pdf == 0. {
+            return 0.;
+        }
+        let hr = match self.object.intersect(&local, 0.001) {
+            Some(hr) => hr,
+            None => return 0.,
+        };
+
+        // Both rays reach the hit at the same parameter, since `local.dir` isn't normalized
+        let local_length = local.dir.length();
+        let local_cosine = hr.normal.dot(&local.dir).abs() / (hr.normal.length() * local_length);
+        let area_pdf = pdf * local_cosine / (hr.dist * local_length).powi(2);
+
+        let world_normal = self.transform.normal(hr.normal);
+        let length = dir.length();
+        let cosine = world_normal.dot(dir).abs() / (world_normal.length() * length);
+        if cosine == 0. {
+            return 0.;
+        }
+        let world_area_pdf = area_pdf / self.transform.area_scale(hr.normal);
+        world_area_pdf * (hr.dist * length).powi(2) / cosine
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        let origin = self.transform.inverse.point(*origin);
+        self.transform
+            .vector(self.object.sample_direction(&origin, sampler))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::objects::rect::XzRect;
+    use crate::textures::color::SolidColor;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use rgb::RGB;
+    use std::f64::consts::PI;
+
+    // The PDF over directions must integrate to 1 over the sphere, scaled or not
+    #[test]
+    fn scaled_light_pdf_integrates_to_one() {
+        let light = XzRect::new(
+            -1.,
+            1.,
+            -1.,
+            1.,
+            0.,
+            DiffuseLight::new(SolidColor::new(RGB::new(1., 1., 1.))),
+        );
+        let transform = Transform::scale(Vector3::new(3., 1., 0.5))
+            .then(&Transform::rotate_x(30.))
+            .then(&Transform::translate(Vector3::new(0., 2., 0.)));
+        let instance = Instance::new(Arc::new(light), transform);
+
+        let origin = Vector3::new(0.5, 0., 0.3);
+        let mut rng = StdRng::seed_from_u64(1);
+        let n = 400_000;
+        let mut sum = 0.;
+        for _ in 0..n {
+            let z: f64 = rng.gen_range(-1., 1.);
+            let phi = rng.gen_range(0., 2. * PI);
+            let r = (1. - z * z).sqrt();
+            let dir = Vector3::new(r * phi.cos(), r * phi.sin(), z);
+            sum += instance.pdf_value(&origin, &dir) * 4. * PI;
+        }
+        let integral = sum / n as f64;
+        assert!((integral - 1.).abs() < 0.01, "integral was {}", integral);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::distribution::Distribution1D;
+use crate::materials::Material;
+use crate::objects::aabb::Aabb;
+use crate::objects::bvh::Bvh;
+use crate::objects::triangle::{intersect_triangle, sample_triangle, triangle_aabb};
+use crate::objects::{HitList, HitResult, Hittable};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use std::sync::Arc;
+
+// Indices of a triangle's corners into the mesh's vertex buffers.
+// Normals and texture coordinates are indexed separately, as in OBJ files.
+#[derive(Copy, Clone, Debug)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+struct MeshData {
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    uvs: Vec<(f64, f64)>,
+    faces: Vec<MeshFace>,
+    mat: Arc<dyn Material>,
+    // Total surface area, over which lights are sampled uniformly
+    area: f64,
+}
+
+// Triangles sharing vertex, normal and uv buffers, with their own BVH
+pub struct TriangleMesh {
+    mesh: Arc<MeshData>,
+    triangles: Bvh,
+    // Picks faces in proportion to their area
+    areas: Distribution1D,
+}
+
+// One face of a mesh, as stored in the mesh's BVH
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl TriangleMesh {
+    // Panics if a face refers to a vertex, normal or uv that doesn't exist
+    pub fn new(
+        positions: Vec<Vector3>,
+        normals: Vec<Vector3>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<MeshFace>,
+        mat: impl Material + 'static,
+    ) -> TriangleMesh {
+        for (i, face) in faces.iter().enumerate() {
+            let in_range = |indices: Option<[usize; 3]>, len: usize| {
+                indices.is_none_or(|idx| idx.iter().all(|&j| j < len))
+            };
+            assert!(
+                in_range(Some(face.positions), positions.len())
+                    && in_range(face.normals, normals.len())
+                    && in_range(face.uvs, uvs.len()),
+                "TriangleMesh: face {} is out of range",
+                i
+            );
+        }
+
+        let areas: Vec<f64> = faces
+            .iter()
+            .map(|face| triangle_area(&face_vertices(&positions, face)))
+            .collect();
+        let mesh = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            faces,
+            mat: Arc::new(mat),
+            area: areas.iter().sum(),
+        });
+
+        let mut list = HitList::new();
+        for face in 0..mesh.faces.len() {
+            list.add(MeshTriangle {
+                mesh: Arc::clone(&mesh),
+                face,
+            });
+        }
+
+        TriangleMesh {
+            mesh,
+            triangles: Bvh::new(list),
+            // Distribution1D needs at least one value
+            areas: Distribution1D::new(if areas.is_empty() { vec![0.] } else { areas }),
+        }
+    }
+}
+
+fn face_vertices(positions: &[Vector3], face: &MeshFace) -> [Vector3; 3] {
+    let idx = face.positions;
+    [positions[idx[0]], positions[idx[1]], positions[idx[2]]]
+}
+
+fn triangle_area(vertices: &[Vector3; 3]) -> f64 {
+    (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .length()
+        / 2.
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [Vector3; 3] {
+        face_vertices(&self.mesh.positions, &self.mesh.faces[self.face])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        let vertices = self.vertices();
+        let (t, b1, b2) = intersect_triangle(ray, min, &vertices)?;
+        let b0 = 1.0 - b1 - b2;
+        let face = &self.mesh.faces[self.face];
+
+        let geometric = (vertices[1] - vertices[0])
+            .cross(&(vertices[2] - vertices[0]))
+            .unit_vec();
+        let front = ray.dir.dot(&geometric) < 0.0;
+
+        // Interpolated shading normal, kept on the same side as the geometric one
+        let mut normal = match face.normals {
+            Some(idx) => {
+                let n = self.mesh.normals[idx[0]] * b0
+                    + self.mesh.normals[idx[1]] * b1
+                    + self.mesh.normals[idx[2]] * b2;
+                if n.length() > 0.0 {
+                    n.unit_vec()
+                } else {
+                    geometric
+                }
+            }
+            None => geometric,
+        };
+        if normal.dot(&geometric) < 0.0 {
+            normal = -normal;
+        }
+
+        let (u, v) = match face.uvs {
+            Some(idx) => {
+                let uv = [
+                    self.mesh.uvs[idx[0]],
+                    self.mesh.uvs[idx[1]],
+                    self.mesh.uvs[idx[2]],
+                ];
+                (
+                    uv[0].0 * b0 + uv[1].0 * b1 + uv[2].0 * b2,
+                    uv[0].1 * b0 + uv[1].1 * b1 + uv[2].1 * b2,
+                )
+            }
+            None => (b1, b2),
+        };
+
+        Some(HitResult::new(
+            t,
+            ray.at(t),
+            u,
+            v,
+            if front { normal } else { -normal },
+            front,
+            Arc::clone(&self.mesh.mat),
+        ))
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Some(triangle_aabb(&self.vertices()))
+    }
+
+    // Density of this face's points when sampling uniformly over the whole mesh.
+    // Uses the geometric normal, since that's what the area is measured against.
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        let vertices = self.vertices();
+        let (t, _, _) = match intersect_triangle(&Ray::new(*origin, *dir), 0.001, &vertices) {
+            Some(hit) => hit,
+            None => return 0.,
+        };
+        let normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0]));
+        let length = dir.length();
+        let distance_squared = t * t * length * length;
+        let cosine = (dir.dot(&normal) / (length * normal.length())).abs();
+        distance_squared / (cosine * self.mesh.area)
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.triangles.intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.triangles.aabb()
+    }
+
+    // Only counts the closest face along `dir`, which is exact for convex meshes
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        if self.mesh.area <= 0. {
+            return 0.;
+        }
+        match self
+            .triangles
+            .intersect_object(&Ray::new(*origin, *dir), 0.001)
+        {
+            Some((_, face)) => face.pdf_value(origin, dir),
+            None => 0.,
+        }
+    }
+
+    // Uniform over the mesh's area: a face in proportion to its area, then a point on it
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        if self.mesh.faces.is_empty() {
+            return Vector3::new(1., 0., 0.);
+        }
+        let (_, face) = self.areas.sample(sampler.get_1d());
+        let vertices = face_vertices(&self.mesh.positions, &self.mesh.faces[face]);
+        sample_triangle(&vertices, sampler.get_2d()) - *origin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::diffuse_light::DiffuseLight;
+    use crate::samplers::independent::IndependentSampler;
+    use crate::textures::color::SolidColor;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use rgb::RGB;
+    use std::f64::consts::PI;
+
+    // A square at y = 2 split into faces of very different sizes
+    fn light() -> TriangleMesh {
+        let positions = vec![
+            Vector3::new(-1., 2., -1.),
+            Vector3::new(1., 2., -1.),
+            Vector3::new(1., 2., 1.),
+            Vector3::new(-1., 2., 1.),
+            Vector3::new(0.8, 2., -1.),
+        ];
+        let face = |positions| MeshFace {
+            positions,
+            normals: None,
+            uvs: None,
+        };
+        TriangleMesh::new(
+            positions,
+            Vec::new(),
+            Vec::new(),
+            vec![face([0, 4, 3]), face([4, 1, 2]), face([4, 2, 3])],
+            DiffuseLight::new(SolidColor::new(RGB::new(1., 1., 1.))),
+        )
+    }
+
+    fn random_dir(rng: &mut StdRng) -> Vector3 {
+        let z: f64 = rng.gen_range(-1., 1.);
+        let phi = rng.gen_range(0., 2. * PI);
+        let r = (1. - z * z).sqrt();
+        Vector3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    #[test]
+    fn light_pdf_integrates_to_one() {
+        let mesh = light();
+        let origin = Vector3::new(0.3, 0., -0.2);
+        let mut rng = StdRng::seed_from_u64(1);
+        let n = 400_000;
+        let mut sum = 0.;
+        for _ in 0..n {
+            sum += mesh.pdf_value(&origin, &random_dir(&mut rng)) * 4. * PI;
+        }
+        let integral = sum / n as f64;
+        assert!((integral - 1.).abs() < 0.01, "integral was {}", integral);
+    }
+
+    // Averaging 1 / pdf over sampled directions gives the solid angle the mesh covers,
+    // which only works out if the samples are drawn with the density pdf_value reports
+    #[test]
+    fn sampled_directions_match_pdf() {
+        let mesh = light();
+        let origin = Vector3::new(0.3, 0., -0.2);
+        let mut rng = StdRng::seed_from_u64(2);
+        let n = 400_000;
+        let hits = (0..n)
+            .filter(|_| {
+                let ray = Ray::new(origin, random_dir(&mut rng));
+                mesh.intersect(&ray, 0.001).is_some()
+            })
+            .count();
+        let solid_angle = hits as f64 / n as f64 * 4. * PI;
+
+        let mut sampler = IndependentSampler::new();
+        sampler.start_sample(2, (0, 0), 0, 1);
+        let mut sum = 0.;
+        for _ in 0..n {
+            let dir = mesh.sample_direction(&origin, &mut sampler);
+            let pdf = mesh.pdf_value(&origin, &dir);
+            assert!(pdf > 0.);
+            sum += 1. / pdf;
+        }
+        let estimate = sum / n as f64;
+        assert!(
+            (estimate / solid_angle - 1.).abs() < 0.01,
+            "{} vs {}",
+            estimate,
+            solid_angle
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::materials::Material;
+use crate::objects::aabb::Aabb;
+use crate::objects::{HitResult, Hittable};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use std::sync::Arc;
+
+// Rectangles are infinitely thin, so their bounding boxes are padded along the normal
+const PAD: f64 = 0.0001;
+
+// Rectangle in the plane z = k
+pub struct XyRect {
+    rect: AxisRect,
+}
+
+// Rectangle in the plane y = k
+pub struct XzRect {
+    rect: AxisRect,
+}
+
+// Rectangle in the plane x = k
+pub struct YzRect {
+    rect: AxisRect,
+}
+
+// Rectangle spanning [a0, a1] x [b0, b1] on the `a` and `b` axes, at `k` on the remaining axis.
+// u runs along `a` and v along `b`.
+struct AxisRect {
+    a: usize,
+    b: usize,
+    k_axis: usize,
+    a0: f64,
+    a1: f64,
+    b0: f64,
+    b1: f64,
+    k: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl XyRect {
+    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, mat: impl Material + 'static) -> XyRect {
+        XyRect {
+            rect: AxisRect::new((0, x0, x1), (1, y0, y1), (2, k), Arc::new(mat)),
+        }
+    }
+}
+
+impl XzRect {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, mat: impl Material + 'static) -> XzRect {
+        XzRect {
+            rect: AxisRect::new((0, x0, x1), (2, z0, z1), (1, k), Arc::new(mat)),
+        }
+    }
+}
+
+impl YzRect {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, mat: impl Material + 'static) -> YzRect {
+        YzRect {
+            rect: AxisRect::new((1, y0, y1), (2, z0, z1), (0, k), Arc::new(mat)),
+        }
+    }
+}
+
+impl AxisRect {
+    fn new(
+        (a, a0, a1): (usize, f64, f64),
+        (b, b0, b1): (usize, f64, f64),
+        (k_axis, k): (usize, f64),
+        mat: Arc<dyn Material>,
+    ) -> AxisRect {
+        AxisRect {
+            a,
+            b,
+            k_axis,
+            a0: a0.min(a1),
+            a1: a0.max(a1),
+            b0: b0.min(b1),
+            b1: b0.max(b1),
+            k,
+            mat,
+        }
+    }
+
+    fn axis_vec(axis: usize, value: f64) -> Vector3 {
+        match axis {
+            0 => Vector3::new(value, 0.0, 0.0),
+            1 => Vector3::new(0.0, value, 0.0),
+            _ => Vector3::new(0.0, 0.0, value),
+        }
+    }
+
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        let t = (self.k - ray.origin[self.k_axis]) / ray.dir[self.k_axis];
+        if !t.is_finite() || t < min {
+            return None;
+        }
+
+        let hp = ray.at(t);
+        let a = hp[self.a];
+        let b = hp[self.b];
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+
+        let u = (a - self.a0) / (self.a1 - self.a0);
+        let v = (b - self.b0) / (self.b1 - self.b0);
+        let normal = AxisRect::axis_vec(self.k_axis, 1.0);
+        let face = ray.dir.dot(&normal) < 0.0;
+
+        Some(HitResult::new(
+            t,
+            hp,
+            u,
+            v,
+            if face { normal } else { -normal },
+            face,
+            Arc::clone(&self.mat),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        let hr = match self.intersect(&Ray::new(*origin, *dir), 0.001) {
+            Some(hr) => hr,
+            None => return 0.,
+        };
+        let area = (self.a1 - self.a0) * (self.b1 - self.b0);
+        let length = dir.length();
+        let distance_squared = hr.dist * hr.dist * length * length;
+        let cosine = (dir[self.k_axis] / length).abs();
+        distance_squared / (cosine * area)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        let (s, t) = sampler.get_2d();
+        let point = AxisRect::axis_vec(self.a, self.a0 + s * (self.a1 - self.a0))
+            + AxisRect::axis_vec(self.b, self.b0 + t * (self.b1 - self.b0))
+            + AxisRect::axis_vec(self.k_axis, self.k);
+        point - *origin
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        let min = AxisRect::axis_vec(self.a, self.a0)
+            + AxisRect::axis_vec(self.b, self.b0)
+            + AxisRect::axis_vec(self.k_axis, self.k - PAD);
+        let max = AxisRect::axis_vec(self.a, self.a1)
+            + AxisRect::axis_vec(self.b, self.b1)
+            + AxisRect::axis_vec(self.k_axis, self.k + PAD);
+        Some(Aabb::new(min, max))
+    }
+}
+
+impl Hittable for XyRect {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.rect.intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.rect.aabb()
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        self.rect.pdf_value(origin, dir)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        self.rect.sample_direction(origin, sampler)
+    }
+}
+
+impl Hittable for XzRect {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.rect.intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.rect.aabb()
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        self.rect.pdf_value(origin, dir)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        self.rect.sample_direction(origin, sampler)
+    }
+}
+
+impl Hittable for YzRect {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        self.rect.intersect(ray, min)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        self.rect.aabb()
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        self.rect.pdf_value(origin, dir)
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        self.rect.sample_direction(origin, sampler)
+    }
+}
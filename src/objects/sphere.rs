@@ -1,8 +1,12 @@
 use crate::materials::Material;
+use crate::objects::aabb::Aabb;
 use crate::objects::{HitResult, Hittable};
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::samplers::Sampler;
 use crate::vec3::Vector3;
 
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 pub struct Sphere {
@@ -74,4 +78,41 @@ impl Hittable for Sphere {
             None
         }
     }
+
+    // Samples the cone of directions the sphere covers as seen from `origin`
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        let to_center = self.center - *origin;
+        let distance_squared = to_center.dot(&to_center);
+        let r_squared = self.r * self.r;
+        if distance_squared <= r_squared {
+            return 1. / (4. * PI);
+        }
+        if self.intersect(&Ray::new(*origin, *dir), 0.001).is_none() {
+            return 0.;
+        }
+        let cos_theta_max = (1. - r_squared / distance_squared).sqrt();
+        1. / (2. * PI * (1. - cos_theta_max))
+    }
+
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        let to_center = self.center - *origin;
+        let distance_squared = to_center.dot(&to_center);
+        let r_squared = self.r * self.r;
+        // From inside, every direction hits the sphere
+        if distance_squared <= r_squared {
+            return Vector3::random_unit_vec(sampler);
+        }
+
+        let (r1, r2) = sampler.get_2d();
+        let cos_theta_max = (1. - r_squared / distance_squared).sqrt();
+        let z = 1. + r2 * (cos_theta_max - 1.);
+        let phi = 2. * PI * r1;
+        let sin_theta = (1. - z * z).sqrt();
+        Onb::from_w(&to_center).local(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        let r = Vector3::new(self.r.abs(), self.r.abs(), self.r.abs());
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
 }
@@ -0,0 +1,118 @@
+use crate::materials::Material;
+use crate::objects::aabb::Aabb;
+use crate::objects::{HitResult, Hittable};
+use crate::ray::Ray;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+use std::sync::Arc;
+
+const EPSILON: f64 = 1e-9;
+
+pub struct Triangle {
+    pub vertices: [Vector3; 3],
+    pub mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Vector3, b: Vector3, c: Vector3, mat: impl Material + 'static) -> Triangle {
+        Triangle {
+            vertices: [a, b, c],
+            mat: Arc::new(mat),
+        }
+    }
+}
+
+// Möller–Trumbore intersection. Returns the distance and the barycentric
+// coordinates of the hit point relative to the second and third vertices.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    min: f64,
+    vertices: &[Vector3; 3],
+) -> Option<(f64, f64, f64)> {
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+    let p = ray.dir.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin - vertices[0];
+    let b1 = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q = s.cross(&edge1);
+    let b2 = ray.dir.dot(&q) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inv_det;
+    if t < min {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+// Maps a point in the unit square uniformly onto the triangle
+pub(crate) fn sample_triangle(vertices: &[Vector3; 3], (r1, r2): (f64, f64)) -> Vector3 {
+    let s = r1.sqrt();
+    let (b1, b2) = (s * (1. - r2), s * r2);
+    vertices[0] + (vertices[1] - vertices[0]) * b1 + (vertices[2] - vertices[0]) * b2
+}
+
+pub(crate) fn triangle_aabb(vertices: &[Vector3; 3]) -> Aabb {
+    // Pad so that triangles lying in an axis plane still have a volume
+    let pad = Vector3::new(0.0001, 0.0001, 0.0001);
+    let min = vertices[0].min(&vertices[1]).min(&vertices[2]);
+    let max = vertices[0].max(&vertices[1]).max(&vertices[2]);
+    Aabb::new(min - pad, max + pad)
+}
+
+impl Hittable for Triangle {
+    fn intersect(&self, ray: &Ray, min: f64) -> Option<HitResult> {
+        let (t, b1, b2) = intersect_triangle(ray, min, &self.vertices)?;
+
+        let normal = (self.vertices[1] - self.vertices[0])
+            .cross(&(self.vertices[2] - self.vertices[0]))
+            .unit_vec();
+        let face = ray.dir.dot(&normal) < 0.0;
+
+        Some(HitResult::new(
+            t,
+            ray.at(t),
+            b1,
+            b2,
+            if face { normal } else { -normal },
+            face,
+            Arc::clone(&self.mat),
+        ))
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Some(triangle_aabb(&self.vertices))
+    }
+
+    fn pdf_value(&self, origin: &Vector3, dir: &Vector3) -> f64 {
+        let hr = match self.intersect(&Ray::new(*origin, *dir), 0.001) {
+            Some(hr) => hr,
+            None => return 0.,
+        };
+        let cross =
+            (self.vertices[1] - self.vertices[0]).cross(&(self.vertices[2] - self.vertices[0]));
+        let area = cross.length() / 2.;
+        let length = dir.length();
+        let distance_squared = hr.dist * hr.dist * length * length;
+        let cosine = (dir.dot(&hr.normal) / length).abs();
+        distance_squared / (cosine * area)
+    }
+
+    // Uniform over the triangle's area
+    fn sample_direction(&self, origin: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        sample_triangle(&self.vertices, sampler.get_2d()) - *origin
+    }
+}
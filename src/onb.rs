@@ -0,0 +1,31 @@
+use crate::vec3::Vector3;
+
+// Orthonormal basis with `w` along a given direction, usually a surface normal
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    pub fn from_w(n: &Vector3) -> Onb {
+        let w = n.unit_vec();
+        let a = if w.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).unit_vec();
+        let u = w.cross(&v);
+        Onb { u, v, w }
+    }
+
+    // Converts coordinates in this basis to world space
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vector3 {
+        self.u * a + self.v * b + self.w * c
+    }
+
+    pub fn local_vec(&self, a: &Vector3) -> Vector3 {
+        self.local(a.x, a.y, a.z)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::checkpoint::CheckpointError;
+
+use image::hdr::HDREncoder;
+use image::{ImageError, Rgb};
+use rgb::RGB;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Floating point formats that keep radiance above 1.0
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HdrFormat {
+    OpenExr,
+    Radiance,
+    Pfm,
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    Io(std::io::Error),
+    Image(ImageError),
+    Exr(exr::error::Error),
+    Checkpoint(CheckpointError),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::Io(e) => write!(f, "{}", e),
+            OutputError::Image(e) => write!(f, "{}", e),
+            OutputError::Exr(e) => write!(f, "{}", e),
+            OutputError::Checkpoint(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+impl From<std::io::Error> for OutputError {
+    fn from(e: std::io::Error) -> Self {
+        OutputError::Io(e)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(e: ImageError) -> Self {
+        OutputError::Image(e)
+    }
+}
+
+impl From<exr::error::Error> for OutputError {
+    fn from(e: exr::error::Error) -> Self {
+        OutputError::Exr(e)
+    }
+}
+
+impl From<CheckpointError> for OutputError {
+    fn from(e: CheckpointError) -> Self {
+        OutputError::Checkpoint(e)
+    }
+}
+
+impl HdrFormat {
+    // Picks the format from the file extension; None for anything else
+    pub fn from_path(path: impl AsRef<Path>) -> Option<HdrFormat> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "exr" => Some(HdrFormat::OpenExr),
+            "hdr" => Some(HdrFormat::Radiance),
+            "pfm" => Some(HdrFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+// Writes linear radiance, one pixel per entry in rows from top to bottom
+pub fn write_hdr(
+    path: impl AsRef<Path>,
+    format: HdrFormat,
+    width: u32,
+    height: u32,
+    pixels: &[RGB<f64>],
+) -> Result<(), OutputError> {
+    let path = path.as_ref();
+    let (width, height) = (width as usize, height as usize);
+    assert_eq!(pixels.len(), width * height);
+
+    match format {
+        HdrFormat::OpenExr => {
+            exr::prelude::write_rgb_file(path, width, height, |x, y| {
+                let px = pixels[y * width + x];
+                (px.r as f32, px.g as f32, px.b as f32)
+            })?;
+        }
+        HdrFormat::Radiance => {
+            let data: Vec<Rgb<f32>> = pixels
+                .iter()
+                .map(|px| Rgb([px.r as f32, px.g as f32, px.b as f32]))
+                .collect();
+            let file = BufWriter::new(File::create(path)?);
+            HDREncoder::new(file).encode(&data, width, height)?;
+        }
+        HdrFormat::Pfm => {
+            // Negative scale means little-endian. Rows are stored bottom to top.
+            let mut file = BufWriter::new(File::create(path)?);
+            write!(file, "PF\n{} {}\n-1.0\n", width, height)?;
+            for row in pixels.chunks(width).rev() {
+                for px in row {
+                    for c in &[px.r, px.g, px.b] {
+                        file.write_all(&(*c as f32).to_le_bytes())?;
+                    }
+                }
+            }
+            file.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::onb::Onb;
+use crate::samplers::Sampler;
+use crate::vec3::Vector3;
+
+// Probability density over directions, with respect to solid angle
+pub trait Pdf {
+    fn value(&self, dir: &Vector3) -> f64;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vector3;
+}
+
+// Proportional to the cosine of the angle with a normal
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vector3) -> CosinePdf {
+        CosinePdf {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, dir: &Vector3) -> f64 {
+        let cosine = dir.unit_vec().dot(&self.uvw.w);
+        if cosine > 0.0 {
+            cosine / std::f64::consts::PI
+        } else {
+            0.0
+        }
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vector3 {
+        self.uvw
+            .local_vec(&Vector3::random_cosine_direction(sampler))
+    }
+}
+
+// Multiple importance sampling weights for a sample drawn from strategy `f`,
+// when strategy `g` could also have produced it
+pub fn balance_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    if f_pdf + g_pdf <= 0. {
+        return 0.;
+    }
+    f_pdf / (f_pdf + g_pdf)
+}
+
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f = f_pdf * f_pdf;
+    let g = g_pdf * g_pdf;
+    if f + g <= 0. {
+        return 0.;
+    }
+    f / (f + g)
+}
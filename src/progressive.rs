@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+// Renders in passes that each add `pass_spp` samples to every pixel, saving the
+// image as it goes. Stops once the render's spp is reached or `time_limit` passes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Progressive {
+    pub pass_spp: u32,
+    // Minimum time between saved images; None saves after every pass
+    pub snapshot_interval: Option<Duration>,
+    // Checked between passes, so the last pass can run over
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Progressive {
+            pass_spp: 1,
+            snapshot_interval: None,
+            time_limit: None,
+        }
+    }
+}
+
+// How far a render has got, reported after each pass
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Pass {
+    // Samples per pixel so far, not counting pixels that adaptive sampling stopped
+    pub spp: u32,
+    // Samples per pixel the render will stop at
+    pub max_spp: u32,
+    // Since the render started or was resumed
+    pub elapsed: Duration,
+}
+
+pub type PassCallback = Box<dyn Fn(&Pass) + Send + Sync>;
@@ -0,0 +1,228 @@
+pub mod halton;
+pub mod independent;
+pub mod sobol;
+pub mod stratified;
+
+// Supplies the random numbers for one sample of a pixel as a sequence of dimensions:
+// the pixel position, the lens, then whatever each bounce asks for. Samplers other
+// than the independent one spread these out more evenly across a pixel's samples.
+pub trait Sampler: Send + Sync {
+    // As given to `from_name`
+    fn name(&self) -> &'static str;
+
+    // Starts sample `index` of `pixel`, out of `spp` samples if known.
+    // Dimensions are handed out from the first one again.
+    fn start_sample(&mut self, seed: u64, pixel: (u32, u32), index: u32, spp: u32);
+
+    fn get_1d(&mut self) -> f64;
+
+    fn get_2d(&mut self) -> (f64, f64);
+
+    // Copy with its own state, for another thread
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+pub const NAMES: [&str; 4] = ["independent", "stratified", "halton", "sobol"];
+
+pub fn from_name(name: &str) -> Option<Box<dyn Sampler>> {
+    match name {
+        "independent" => Some(Box::new(independent::IndependentSampler::new())),
+        "stratified" => Some(Box::new(stratified::StratifiedSampler::new())),
+        "halton" => Some(Box::new(halton::HaltonSampler::new())),
+        "sobol" => Some(Box::new(sobol::SobolSampler::new())),
+        _ => None,
+    }
+}
+
+// Combines values into a well mixed 64-bit hash
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0, |h, v| mix(h ^ mix(*v)))
+}
+
+// Uniform value in [0, 1) from a hash
+pub(crate) fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 * (1. / (1u64 << 53) as f64)
+}
+
+pub(crate) fn pixel_id(pixel: (u32, u32)) -> u64 {
+    (pixel.1 as u64) << 32 | pixel.0 as u64
+}
+
+// Element `i` of a random permutation of 0..n, chosen by `seed`.
+// Kensler, "Correlated Multi-Jittered Sampling" (2013).
+pub(crate) fn permute(mut i: u32, n: u32, seed: u32) -> u32 {
+    if n <= 1 {
+        return 0;
+    }
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    (i.wrapping_add(seed)) % n
+}
+
+// SplitMix64 finalizer
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws `dims` values for one sample, alternating 1D and 2D dimensions
+    fn draw(sampler: &mut dyn Sampler, pixel: (u32, u32), index: u32, dims: usize) -> Vec<f64> {
+        sampler.start_sample(7, pixel, index, 16);
+        let mut values = Vec::new();
+        while values.len() < dims {
+            if values.len() % 3 == 0 {
+                values.push(sampler.get_1d());
+            } else {
+                let (x, y) = sampler.get_2d();
+                values.push(x);
+                values.push(y);
+            }
+        }
+        values
+    }
+
+    // Checks that each of the `nx` by `ny` cells of the unit square holds one point
+    fn assert_stratified(points: &[(f64, f64)], nx: usize, ny: usize, what: &str) {
+        assert_eq!(points.len(), nx * ny);
+        let mut seen = vec![false; nx * ny];
+        for &(x, y) in points {
+            let cell = (y * ny as f64) as usize * nx + (x * nx as f64) as usize;
+            assert!(
+                !seen[cell],
+                "{}: two points in one of {} x {} cells",
+                what, nx, ny
+            );
+            seen[cell] = true;
+        }
+    }
+
+    // The first `n` values of dimension `dim`, which must be a 1D one
+    fn first_1d(sampler: &mut dyn Sampler, dim: usize, n: u32, spp: u32) -> Vec<(f64, f64)> {
+        (0..n)
+            .map(|index| {
+                sampler.start_sample(7, (3, 5), index, spp);
+                for _ in 0..dim {
+                    sampler.get_1d();
+                }
+                (sampler.get_1d(), 0.)
+            })
+            .collect()
+    }
+
+    // The first `n` points of the 2D dimension after `dim` 1D ones
+    fn first_2d(sampler: &mut dyn Sampler, dim: usize, n: u32, spp: u32) -> Vec<(f64, f64)> {
+        (0..n)
+            .map(|index| {
+                sampler.start_sample(7, (3, 5), index, spp);
+                for _ in 0..dim {
+                    sampler.get_1d();
+                }
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn samples_are_in_unit_interval() {
+        for name in &NAMES {
+            let mut sampler = from_name(name).unwrap();
+            for &(pixel, index) in &[((0, 0), 0), ((17, 3), 5), ((9, 9), 40), ((1, 2), 1 << 20)] {
+                // Past the Halton sampler's primes too
+                for v in draw(sampler.as_mut(), pixel, index, 300) {
+                    assert!((0. ..1.).contains(&v), "{}: {} is out of range", name, v);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_deterministic() {
+        for name in &NAMES {
+            let mut a = from_name(name).unwrap();
+            let mut b = a.clone_box();
+            // Drawing other samples in between mustn't change anything
+            draw(b.as_mut(), (4, 4), 2, 10);
+            for &(pixel, index) in &[((0, 0), 0), ((17, 3), 5), ((9, 9), 40)] {
+                let first = draw(a.as_mut(), pixel, index, 40);
+                assert_eq!(first, draw(b.as_mut(), pixel, index, 40), "{}", name);
+                assert_eq!(first, draw(a.as_mut(), pixel, index, 40), "{}", name);
+                assert_ne!(first, draw(a.as_mut(), (pixel.0 + 1, pixel.1), index, 40));
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_sampler_is_stratified() {
+        let mut sampler = stratified::StratifiedSampler::new();
+        for &dim in &[0, 3] {
+            assert_stratified(&first_1d(&mut sampler, dim, 16, 16), 16, 1, "1D");
+            assert_stratified(&first_2d(&mut sampler, dim, 16, 16), 4, 4, "2D");
+            assert_stratified(&first_1d(&mut sampler, dim, 10, 10), 10, 1, "1D");
+        }
+
+        // Every round of `spp` samples is stratified on its own
+        let points: Vec<_> = (16..32)
+            .map(|index| {
+                sampler.start_sample(7, (3, 5), index, 16);
+                (sampler.get_1d(), 0.)
+            })
+            .collect();
+        assert_stratified(&points, 16, 1, "second round");
+    }
+
+    #[test]
+    fn halton_sampler_is_stratified() {
+        let mut sampler = halton::HaltonSampler::new();
+        // Dimensions 0 and 1 have bases 2 and 3
+        assert_stratified(&first_1d(&mut sampler, 0, 16, 16), 16, 1, "base 2");
+        assert_stratified(&first_1d(&mut sampler, 1, 27, 27), 27, 1, "base 3");
+        assert_stratified(&first_1d(&mut sampler, 4, 11, 11), 11, 1, "base 11");
+        assert_stratified(&first_2d(&mut sampler, 0, 72, 72), 8, 9, "bases 2 and 3");
+    }
+
+    #[test]
+    fn sobol_sampler_is_stratified() {
+        let mut sampler = sobol::SobolSampler::new();
+        for &dim in &[0, 3] {
+            assert_stratified(&first_1d(&mut sampler, dim, 16, 16), 16, 1, "1D");
+            // (0, 4, 2)-net: every elementary interval of area 1/16 holds one point
+            let points = first_2d(&mut sampler, dim, 16, 16);
+            for &(nx, ny) in &[(1, 16), (2, 8), (4, 4), (8, 2), (16, 1)] {
+                assert_stratified(&points, nx, ny, "2D");
+            }
+        }
+    }
+}
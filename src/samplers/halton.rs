@@ -0,0 +1,111 @@
+use crate::samplers::{hash, permute, pixel_id, to_unit, Sampler};
+
+// Dimensions past this many primes fall back to random numbers
+const MAX_DIMENSIONS: usize = 256;
+
+// Halton sequence with one prime base per dimension. The digits are Owen scrambled
+// with a different seed for every pixel and dimension, which breaks up the
+// correlation between dimensions with nearby bases and between neighbouring pixels.
+pub struct HaltonSampler {
+    primes: Vec<u64>,
+    seed: u64,
+    index: u64,
+    // Largest index among this sample and the pixel's first `spp`
+    max_index: u64,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new() -> HaltonSampler {
+        HaltonSampler {
+            primes: primes(MAX_DIMENSIONS),
+            seed: 0,
+            index: 0,
+            max_index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Default for HaltonSampler {
+    fn default() -> Self {
+        HaltonSampler::new()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn name(&self) -> &'static str {
+        "halton"
+    }
+
+    fn start_sample(&mut self, seed: u64, pixel: (u32, u32), index: u32, spp: u32) {
+        self.seed = hash(&[seed, pixel_id(pixel)]);
+        self.index = index as u64;
+        self.max_index = index.max(spp.saturating_sub(1)) as u64;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dim = self.dimension;
+        self.dimension += 1;
+        if dim >= self.primes.len() {
+            return to_unit(hash(&[self.seed, dim as u64, self.index]));
+        }
+        let seed = hash(&[self.seed, dim as u64]);
+        scrambled_radical_inverse(self.index, self.max_index, self.primes[dim], seed)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(HaltonSampler {
+            primes: self.primes.clone(),
+            seed: 0,
+            index: 0,
+            max_index: 0,
+            dimension: 0,
+        })
+    }
+}
+
+// Mirrors the digits of `i` in `base` around the decimal point, permuting each
+// digit depending on the digits before it
+fn scrambled_radical_inverse(mut i: u64, max_index: u64, base: u64, seed: u64) -> f64 {
+    let inv_base = 1. / base as f64;
+    let mut inv = inv_base;
+    let mut result = 0.;
+    let mut prefix = seed;
+    // Goes through as many digits as `max_index` has, leading zeros included, so that
+    // the first `spp` indices all see the same permutations and stay stratified
+    let mut digits = max_index;
+    while digits > 0 {
+        let digit = i % base;
+        let permuted = permute(digit as u32, base as u32, prefix as u32);
+        result += permuted as f64 * inv;
+        prefix = hash(&[prefix, digit]);
+        i /= base;
+        digits /= base;
+        inv *= inv_base;
+    }
+    // The zeros past the last digit scramble to a uniformly random tail
+    result += to_unit(prefix) * inv * base as f64;
+    result.min(1. - f64::EPSILON)
+}
+
+fn primes(count: usize) -> Vec<u64> {
+    let mut primes: Vec<u64> = Vec::with_capacity(count);
+    let mut n = 2;
+    while primes.len() < count {
+        if primes
+            .iter()
+            .take_while(|p| *p * *p <= n)
+            .all(|p| n % p != 0)
+        {
+            primes.push(n);
+        }
+        n += 1;
+    }
+    primes
+}
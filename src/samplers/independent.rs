@@ -0,0 +1,45 @@
+use crate::samplers::{hash, pixel_id, Sampler};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Plain uniform random numbers, from a generator seeded for each sample
+pub struct IndependentSampler {
+    rng: StdRng,
+}
+
+impl IndependentSampler {
+    pub fn new() -> IndependentSampler {
+        IndependentSampler {
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Default for IndependentSampler {
+    fn default() -> Self {
+        IndependentSampler::new()
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn name(&self) -> &'static str {
+        "independent"
+    }
+
+    fn start_sample(&mut self, seed: u64, pixel: (u32, u32), index: u32, _spp: u32) {
+        self.rng = StdRng::seed_from_u64(hash(&[seed, pixel_id(pixel), index as u64]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.rng.gen(), self.rng.gen())
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(IndependentSampler::new())
+    }
+}
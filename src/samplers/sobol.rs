@@ -0,0 +1,97 @@
+use crate::samplers::{hash, pixel_id, Sampler};
+
+// Owen-scrambled Sobol points, padded: every 2D dimension takes the first two Sobol
+// dimensions with its own scrambling and its own shuffle of the sample order.
+// Burley, "Practical Hash-based Owen Scrambling" (2020).
+pub struct SobolSampler {
+    seed: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new() -> SobolSampler {
+        SobolSampler {
+            seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Scrambled sample index and seeds for the next dimension
+    fn next_dimension(&mut self) -> (u32, u64) {
+        let dim_seed = hash(&[self.seed, self.dimension]);
+        self.dimension += 1;
+        let index = nested_uniform_scramble(self.index, dim_seed as u32);
+        (index, dim_seed)
+    }
+}
+
+impl Default for SobolSampler {
+    fn default() -> Self {
+        SobolSampler::new()
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn name(&self) -> &'static str {
+        "sobol"
+    }
+
+    fn start_sample(&mut self, seed: u64, pixel: (u32, u32), index: u32, _spp: u32) {
+        self.seed = hash(&[seed, pixel_id(pixel)]);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, dim_seed) = self.next_dimension();
+        to_float(nested_uniform_scramble(
+            index.reverse_bits(),
+            (dim_seed >> 32) as u32,
+        ))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, dim_seed) = self.next_dimension();
+        let x = nested_uniform_scramble(index.reverse_bits(), (dim_seed >> 32) as u32);
+        let y = nested_uniform_scramble(sobol_1(index), hash(&[dim_seed]) as u32);
+        (to_float(x), to_float(y))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(SobolSampler::new())
+    }
+}
+
+// Second Sobol dimension; the first is just the bit-reversed index
+fn sobol_1(mut index: u32) -> u32 {
+    let mut v = 1u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+// Owen scrambling: randomly flips each bit depending on the bits above it
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn to_float(x: u32) -> f64 {
+    x as f64 * (1. / 4294967296.)
+}
@@ -0,0 +1,78 @@
+use crate::samplers::{hash, permute, pixel_id, to_unit, Sampler};
+
+// Splits every dimension into one stratum per sample and jitters within it. Each
+// dimension visits its strata in a different random order. 2D dimensions use the
+// largest grid that fits in the sample count; leftover samples are uniform.
+pub struct StratifiedSampler {
+    // Seed for this pixel and round of samples
+    seed: u64,
+    // Position in the shuffled strata
+    index: u32,
+    spp: u32,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new() -> StratifiedSampler {
+        StratifiedSampler {
+            seed: 0,
+            index: 0,
+            spp: 1,
+            dimension: 0,
+        }
+    }
+
+    fn next_dimension(&mut self) -> (u32, u64) {
+        let dim_seed = hash(&[self.seed, self.dimension]);
+        self.dimension += 1;
+        let stratum = permute(self.index, self.spp, dim_seed as u32);
+        (stratum, dim_seed)
+    }
+}
+
+impl Default for StratifiedSampler {
+    fn default() -> Self {
+        StratifiedSampler::new()
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn name(&self) -> &'static str {
+        "stratified"
+    }
+
+    fn start_sample(&mut self, seed: u64, pixel: (u32, u32), index: u32, spp: u32) {
+        // Renders that go past `spp` get a new set of strata for every round
+        let spp = spp.max(1);
+        let round = (index / spp) as u64;
+        self.seed = hash(&[seed, pixel_id(pixel), round]);
+        self.index = index % spp;
+        self.spp = spp;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, dim_seed) = self.next_dimension();
+        let jitter = to_unit(hash(&[dim_seed, self.index as u64]));
+        (stratum as f64 + jitter) / self.spp as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, dim_seed) = self.next_dimension();
+        let nx = (self.spp as f64).sqrt() as u32;
+        let ny = self.spp / nx;
+        let jx = to_unit(hash(&[dim_seed, self.index as u64, 0]));
+        let jy = to_unit(hash(&[dim_seed, self.index as u64, 1]));
+        if stratum >= nx * ny {
+            return (jx, jy);
+        }
+        (
+            ((stratum % nx) as f64 + jx) / nx as f64,
+            ((stratum / nx) as f64 + jy) / ny as f64,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(StratifiedSampler::new())
+    }
+}
@@ -0,0 +1,870 @@
+use crate::camera::Camera;
+use crate::environment::sky::{self, PreethamSky, Sun};
+use crate::environment::{map::EnvironmentMap, Constant, Environment, Gradient};
+use crate::filters::Filter;
+use crate::integrators;
+use crate::materials::{
+    dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian, metal::Metal,
+    Material,
+};
+use crate::obj;
+use crate::objects::bvh::Bvh;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::instance::Instance;
+use crate::objects::rect::{XyRect, XzRect, YzRect};
+use crate::objects::sphere::Sphere;
+use crate::objects::triangle::Triangle;
+use crate::objects::{HitList, Hittable};
+use crate::progressive::Progressive;
+use crate::samplers;
+use crate::textures::{checkers::Checkers, color::SolidColor, image::ImageTexture, Texture};
+use crate::tonemap::ToneMap;
+use crate::transform::Transform;
+use crate::vec3::Vector3;
+use crate::world::World;
+
+use rgb::RGB;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    #[serde(default)]
+    render: RenderDesc,
+    camera: CameraDesc,
+    environment: Option<EnvironmentDesc>,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    objects: Vec<ObjectDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct RenderDesc {
+    width: u32,
+    height: Option<u32>,
+    aspect_ratio: f64,
+    spp: u32,
+    // Enables adaptive sampling
+    adaptive_threshold: Option<f64>,
+    // 8 times `spp` if not given
+    max_spp: Option<u32>,
+    // Enables progressive rendering
+    pass_spp: Option<u32>,
+    // In seconds, only used by progressive renders
+    snapshot_interval: Option<f64>,
+    time_limit: Option<f64>,
+    filter: String,
+    // In pixels. Each filter has its own default.
+    filter_radius: Option<f64>,
+    max_depth: u32,
+    // Depth at which Russian roulette starts
+    rr_depth: u32,
+    // Shorthand for a constant environment
+    background: Option<[f64; 3]>,
+    integrator: String,
+    sampler: String,
+    // Random if not given
+    seed: Option<u64>,
+    tone_map: String,
+    // Only used by the extended_reinhard tone map
+    white_point: f64,
+    exposure: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    from: [f64; 3],
+    lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    vup: [f64; 3],
+    vfov: f64,
+    #[serde(default)]
+    aperture: f64,
+    // Distance from `from` to `lookat` if not given
+    focus_dist: Option<f64>,
+}
+
+// Sky gradient if not given
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum EnvironmentDesc {
+    Gradient {
+        #[serde(default = "default_bottom")]
+        bottom: [f64; 3],
+        #[serde(default = "default_top")]
+        top: [f64; 3],
+    },
+    Constant {
+        color: [f64; 3],
+    },
+    // Equirectangular image, .hdr and .exr files keep their full range
+    Image {
+        path: PathBuf,
+        // Degrees around the y axis
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    // Preetham daylight model with a matching sun
+    Sky {
+        // Towards the sun
+        sun_direction: [f64; 3],
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_ground_albedo")]
+        ground_albedo: [f64; 3],
+        // Degrees. 0 removes the sun and leaves only the sky.
+        #[serde(default = "default_sun_diameter")]
+        sun_diameter: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    // Just the sun, in a black sky
+    Sun {
+        direction: [f64; 3],
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_sun_diameter")]
+        diameter: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid { color: [f64; 3] },
+    Checkers { odd: String, even: String },
+    Image { path: PathBuf },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        texture: Option<String>,
+        color: Option<[f64; 3]>,
+    },
+    Metal {
+        color: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        ref_idx: f64,
+    },
+    DiffuseLight {
+        texture: Option<String>,
+        color: Option<[f64; 3]>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    XyRect {
+        x: [f64; 2],
+        y: [f64; 2],
+        z: f64,
+        material: String,
+    },
+    XzRect {
+        x: [f64; 2],
+        z: [f64; 2],
+        y: f64,
+        material: String,
+    },
+    YzRect {
+        y: [f64; 2],
+        z: [f64; 2],
+        x: f64,
+        material: String,
+    },
+    Box {
+        min: [f64; 3],
+        max: [f64; 3],
+        material: String,
+        #[serde(default)]
+        transform: Vec<TransformStep>,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: String,
+    },
+    // Wavefront OBJ file, using the materials from its .mtl files
+    Obj {
+        path: PathBuf,
+        #[serde(default)]
+        transform: Vec<TransformStep>,
+    },
+}
+
+// Steps are applied in the order they are listed
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformStep {
+    Translate([f64; 3]),
+    Scale([f64; 3]),
+    RotateX(f64),
+    RotateY(f64),
+    RotateZ(f64),
+}
+
+impl Default for RenderDesc {
+    fn default() -> Self {
+        RenderDesc {
+            width: 426,
+            height: None,
+            aspect_ratio: 16. / 9.,
+            spp: 25,
+            adaptive_threshold: None,
+            max_spp: None,
+            pass_spp: None,
+            snapshot_interval: None,
+            time_limit: None,
+            filter: "box".to_string(),
+            filter_radius: None,
+            max_depth: 10,
+            rr_depth: 3,
+            background: None,
+            integrator: "path".to_string(),
+            sampler: "sobol".to_string(),
+            seed: None,
+            tone_map: "clamp".to_string(),
+            white_point: ToneMap::DEFAULT_WHITE,
+            exposure: 0.,
+        }
+    }
+}
+
+fn default_vup() -> [f64; 3] {
+    [0., 1., 0.]
+}
+
+fn default_bottom() -> [f64; 3] {
+    [1., 1., 1.]
+}
+
+fn default_top() -> [f64; 3] {
+    [0.5, 0.75, 1.]
+}
+
+fn default_intensity() -> f64 {
+    1.
+}
+
+fn default_turbidity() -> f64 {
+    3.
+}
+
+fn default_ground_albedo() -> [f64; 3] {
+    [0.3, 0.3, 0.3]
+}
+
+fn default_sun_diameter() -> f64 {
+    sky::SUN_DIAMETER
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    // A value that parsed but doesn't make sense, e.g. a reference to an undefined material
+    Invalid { field: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Parse(e) => write!(f, "{}", e),
+            SceneError::Invalid { field, message } => write!(f, "{}: {}", field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(e: toml::de::Error) -> Self {
+        SceneError::Parse(e)
+    }
+}
+
+fn invalid(field: impl Into<String>, message: impl Into<String>) -> SceneError {
+    SceneError::Invalid {
+        field: field.into(),
+        message: message.into(),
+    }
+}
+
+// Loads a scene file. Relative texture paths are resolved against the file's directory.
+// Also returns warnings about problems that were worked around, like a missing .mtl file.
+pub fn load(path: impl AsRef<Path>) -> Result<(World, Vec<String>), SceneError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    from_str(&source, base_dir)
+}
+
+pub fn from_str(source: &str, base_dir: &Path) -> Result<(World, Vec<String>), SceneError> {
+    let scene: SceneFile = toml::from_str(source)?;
+    let mut builder = Builder {
+        scene: &scene,
+        base_dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+        warnings: Vec::new(),
+    };
+
+    let mut world = builder.world()?;
+    for (i, obj) in scene.objects.iter().enumerate() {
+        builder.add_object(&mut world, obj, &format!("objects[{}]", i))?;
+    }
+    Ok((world, builder.warnings))
+}
+
+struct Builder<'a> {
+    scene: &'a SceneFile,
+    base_dir: &'a Path,
+    textures: HashMap<&'a str, Arc<dyn Texture>>,
+    materials: HashMap<&'a str, Arc<dyn Material>>,
+    // OBJ files are only loaded once, and instanced if used again
+    meshes: HashMap<&'a Path, ObjInstances>,
+    warnings: Vec<String>,
+}
+
+// A loaded OBJ file. Its emissive meshes are kept apart so that each instance of them
+// can be added as a light.
+struct ObjInstances {
+    mesh: Arc<dyn Hittable>,
+    lights: Vec<Arc<dyn Hittable>>,
+}
+
+impl<'a> Builder<'a> {
+    fn world(&self) -> Result<World, SceneError> {
+        let render = &self.scene.render;
+        if render.width == 0 {
+            return Err(invalid("render.width", "must be greater than 0"));
+        }
+        if render.spp == 0 {
+            return Err(invalid("render.spp", "must be greater than 0"));
+        }
+        let adaptive = match (render.adaptive_threshold, render.max_spp) {
+            (Some(t), _) if t <= 0. => {
+                return Err(invalid(
+                    "render.adaptive_threshold",
+                    "must be greater than 0",
+                ))
+            }
+            (Some(_), Some(max)) if max < render.spp => {
+                return Err(invalid("render.max_spp", "must be at least render.spp"))
+            }
+            (Some(t), max) => Some((t, max.unwrap_or(render.spp.saturating_mul(8)))),
+            (None, Some(_)) => {
+                return Err(invalid(
+                    "render.max_spp",
+                    "only applies with render.adaptive_threshold",
+                ))
+            }
+            (None, None) => None,
+        };
+        let height = match render.height {
+            Some(0) => return Err(invalid("render.height", "must be greater than 0")),
+            Some(height) => height,
+            None if render.aspect_ratio > 0. => {
+                ((render.width as f64 / render.aspect_ratio) as u32).max(1)
+            }
+            None => return Err(invalid("render.aspect_ratio", "must be greater than 0")),
+        };
+
+        let cam = &self.scene.camera;
+        if cam.vfov <= 0. || cam.vfov >= 180. {
+            return Err(invalid("camera.vfov", "must be between 0 and 180 degrees"));
+        }
+        let from = vector(cam.from);
+        let lookat = vector(cam.lookat);
+        if (from - lookat).length() == 0. {
+            return Err(invalid("camera.lookat", "must differ from camera.from"));
+        }
+        let focus_dist = cam.focus_dist.unwrap_or_else(|| (from - lookat).length());
+
+        let tone_map =
+            ToneMap::from_name(&render.tone_map, render.white_point).ok_or_else(|| {
+                invalid(
+                    "render.tone_map",
+                    format!(
+                        "unknown tone map \"{}\", expected one of {}",
+                        render.tone_map,
+                        ToneMap::NAMES.join(", ")
+                    ),
+                )
+            })?;
+        let integrator = integrators::from_name(&render.integrator).ok_or_else(|| {
+            invalid(
+                "render.integrator",
+                format!(
+                    "unknown integrator \"{}\", expected one of {}",
+                    render.integrator,
+                    integrators::NAMES.join(", ")
+                ),
+            )
+        })?;
+        let sampler = samplers::from_name(&render.sampler).ok_or_else(|| {
+            invalid(
+                "render.sampler",
+                format!(
+                    "unknown sampler \"{}\", expected one of {}",
+                    render.sampler,
+                    samplers::NAMES.join(", ")
+                ),
+            )
+        })?;
+        let progressive = self.progressive()?;
+        if render.filter_radius.is_some_and(|r| r <= 0.) {
+            return Err(invalid("render.filter_radius", "must be greater than 0"));
+        }
+        let filter = Filter::from_name(&render.filter, render.filter_radius).ok_or_else(|| {
+            invalid(
+                "render.filter",
+                format!(
+                    "unknown filter \"{}\", expected one of {}",
+                    render.filter,
+                    Filter::NAMES.join(", ")
+                ),
+            )
+        })?;
+        if render.white_point <= 0. {
+            return Err(invalid("render.white_point", "must be greater than 0"));
+        }
+
+        let environment = self.environment()?;
+        let cam = Camera::new(
+            from,
+            lookat,
+            vector(cam.vup),
+            cam.vfov,
+            render.width as f64 / height as f64,
+            cam.aperture,
+            focus_dist,
+        );
+
+        let mut builder = World::builder()
+            .width(render.width)
+            .height(height)
+            .spp(render.spp)
+            .max_depth(render.max_depth)
+            .rr_depth(render.rr_depth)
+            .environment(environment)
+            .integrator(integrator)
+            .sampler(sampler)
+            .filter(filter)
+            .tone_map(tone_map)
+            .exposure(render.exposure)
+            .camera(cam);
+        if let Some(seed) = render.seed {
+            builder = builder.seed(seed);
+        }
+        if let Some((threshold, max_spp)) = adaptive {
+            builder = builder.adaptive(threshold, max_spp);
+        }
+        if let Some(progressive) = progressive {
+            builder = builder.progressive(progressive);
+        }
+        Ok(builder.build())
+    }
+
+    fn progressive(&self) -> Result<Option<Progressive>, SceneError> {
+        let render = &self.scene.render;
+        if render.pass_spp.is_none()
+            && render.snapshot_interval.is_none()
+            && render.time_limit.is_none()
+        {
+            return Ok(None);
+        }
+        let seconds = |secs: Option<f64>, field: &str| match secs {
+            Some(secs) if !secs.is_finite() || secs < 0. => {
+                Err(invalid(field, "can't be negative"))
+            }
+            secs => Ok(secs.map(Duration::from_secs_f64)),
+        };
+        let pass_spp = match render.pass_spp {
+            Some(0) => return Err(invalid("render.pass_spp", "must be greater than 0")),
+            pass_spp => pass_spp.unwrap_or(1),
+        };
+        Ok(Some(Progressive {
+            pass_spp,
+            snapshot_interval: seconds(render.snapshot_interval, "render.snapshot_interval")?,
+            time_limit: seconds(render.time_limit, "render.time_limit")?,
+        }))
+    }
+
+    fn environment(&self) -> Result<Arc<dyn Environment>, SceneError> {
+        let desc = match (&self.scene.environment, self.scene.render.background) {
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "render.background",
+                    "can't be combined with an [environment] table",
+                ))
+            }
+            (None, Some(c)) => return Ok(Arc::new(Constant::new(color(c)))),
+            (None, None) => return Ok(Arc::new(Gradient::default())),
+            (Some(desc), None) => desc,
+        };
+
+        Ok(match desc {
+            EnvironmentDesc::Gradient { bottom, top } => {
+                Arc::new(Gradient::new(color(*bottom), color(*top)))
+            }
+            EnvironmentDesc::Constant { color: c } => Arc::new(Constant::new(color(*c))),
+            EnvironmentDesc::Image {
+                path,
+                rotation,
+                intensity,
+            } => {
+                if *intensity < 0. {
+                    return Err(invalid("environment.intensity", "must not be negative"));
+                }
+                let path = self.base_dir.join(path);
+                let map = EnvironmentMap::open(&path).map_err(|e| {
+                    invalid("environment.path", format!("{}: {}", path.display(), e))
+                })?;
+                Arc::new(map.rotation(*rotation).intensity(*intensity))
+            }
+            EnvironmentDesc::Sky {
+                sun_direction,
+                turbidity,
+                ground_albedo,
+                sun_diameter,
+                intensity,
+            } => {
+                check_sun(*sun_direction, *turbidity, *intensity, "sun_direction")?;
+                let sky =
+                    PreethamSky::new(vector(*sun_direction), *turbidity, color(*ground_albedo));
+                Arc::new(sky.sun_diameter(*sun_diameter).intensity(*intensity))
+            }
+            EnvironmentDesc::Sun {
+                direction,
+                turbidity,
+                diameter,
+                intensity,
+            } => {
+                check_sun(*direction, *turbidity, *intensity, "direction")?;
+                if *diameter <= 0. {
+                    return Err(invalid("environment.diameter", "must be greater than 0"));
+                }
+                let sun = Sun::through_atmosphere(vector(*direction), *diameter, *turbidity);
+                Arc::new(sun.intensity(*intensity))
+            }
+        })
+    }
+
+    fn texture(&mut self, name: &'a str, field: &str) -> Result<Arc<dyn Texture>, SceneError> {
+        self.texture_inner(name, field, &mut Vec::new())
+    }
+
+    fn texture_inner(
+        &mut self,
+        name: &'a str,
+        field: &str,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some(tex) = self.textures.get(name) {
+            return Ok(Arc::clone(tex));
+        }
+        let desc = match self.scene.textures.get_key_value(name) {
+            Some((key, desc)) => {
+                if visiting.contains(&key.as_str()) {
+                    return Err(invalid(
+                        field,
+                        format!("texture \"{}\" refers to itself", name),
+                    ));
+                }
+                visiting.push(key.as_str());
+                desc
+            }
+            None => return Err(invalid(field, format!("unknown texture \"{}\"", name))),
+        };
+
+        let prefix = format!("textures.{}", name);
+        let tex: Arc<dyn Texture> = match desc {
+            TextureDesc::Solid { color: c } => Arc::new(SolidColor::new(color(*c))),
+            TextureDesc::Checkers { odd, even } => {
+                let odd = self.texture_inner(odd, &format!("{}.odd", prefix), visiting)?;
+                let even = self.texture_inner(even, &format!("{}.even", prefix), visiting)?;
+                Arc::new(Checkers::new(odd, even))
+            }
+            TextureDesc::Image { path } => {
+                let path = self.base_dir.join(path);
+                let img = ImageTexture::open(&path).map_err(|e| {
+                    invalid(
+                        format!("{}.path", prefix),
+                        format!("{}: {}", path.display(), e),
+                    )
+                })?;
+                Arc::new(img)
+            }
+        };
+        visiting.pop();
+        self.textures.insert(name, Arc::clone(&tex));
+        Ok(tex)
+    }
+
+    // Materials that take a texture accept either a named `texture` or an inline `color`
+    fn texture_or_color(
+        &mut self,
+        texture: &'a Option<String>,
+        c: Option<[f64; 3]>,
+        prefix: &str,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        match (texture, c) {
+            (Some(name), None) => self.texture(name, &format!("{}.texture", prefix)),
+            (None, Some(c)) => Ok(Arc::new(SolidColor::new(color(c)))),
+            _ => Err(invalid(
+                prefix,
+                "exactly one of `texture` or `color` is required",
+            )),
+        }
+    }
+
+    fn material(&mut self, name: &'a str, field: &str) -> Result<Arc<dyn Material>, SceneError> {
+        if let Some(mat) = self.materials.get(name) {
+            return Ok(Arc::clone(mat));
+        }
+        let desc = self
+            .scene
+            .materials
+            .get(name)
+            .ok_or_else(|| invalid(field, format!("unknown material \"{}\"", name)))?;
+
+        let prefix = format!("materials.{}", name);
+        let mat: Arc<dyn Material> = match desc {
+            MaterialDesc::Lambertian { texture, color: c } => Arc::new(Lambertian::new(
+                self.texture_or_color(texture, *c, &prefix)?,
+            )),
+            MaterialDesc::Metal { color: c, fuzz } => {
+                if *fuzz < 0. {
+                    return Err(invalid(format!("{}.fuzz", prefix), "must not be negative"));
+                }
+                Arc::new(Metal::new(color(*c), *fuzz))
+            }
+            MaterialDesc::Dielectric { ref_idx } => {
+                if *ref_idx <= 0. {
+                    return Err(invalid(
+                        format!("{}.ref_idx", prefix),
+                        "must be greater than 0",
+                    ));
+                }
+                Arc::new(Dielectric::new(*ref_idx))
+            }
+            MaterialDesc::DiffuseLight { texture, color: c } => Arc::new(DiffuseLight::new(
+                self.texture_or_color(texture, *c, &prefix)?,
+            )),
+        };
+        self.materials.insert(name, Arc::clone(&mat));
+        Ok(mat)
+    }
+
+    fn add_object(
+        &mut self,
+        world: &mut World,
+        obj: &'a ObjectDesc,
+        prefix: &str,
+    ) -> Result<(), SceneError> {
+        match obj {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                if *radius == 0. {
+                    return Err(invalid(format!("{}.radius", prefix), "must not be 0"));
+                }
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                add(
+                    world,
+                    Sphere::new(vector(*center), *radius, mat),
+                    self.is_light(material),
+                );
+            }
+            ObjectDesc::XyRect { x, y, z, material } => {
+                check_extent(*x, &format!("{}.x", prefix))?;
+                check_extent(*y, &format!("{}.y", prefix))?;
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                add(
+                    world,
+                    XyRect::new(x[0], x[1], y[0], y[1], *z, mat),
+                    self.is_light(material),
+                );
+            }
+            ObjectDesc::XzRect { x, z, y, material } => {
+                check_extent(*x, &format!("{}.x", prefix))?;
+                check_extent(*z, &format!("{}.z", prefix))?;
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                add(
+                    world,
+                    XzRect::new(x[0], x[1], z[0], z[1], *y, mat),
+                    self.is_light(material),
+                );
+            }
+            ObjectDesc::YzRect { y, z, x, material } => {
+                check_extent(*y, &format!("{}.y", prefix))?;
+                check_extent(*z, &format!("{}.z", prefix))?;
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                add(
+                    world,
+                    YzRect::new(y[0], y[1], z[0], z[1], *x, mat),
+                    self.is_light(material),
+                );
+            }
+            ObjectDesc::Box {
+                min,
+                max,
+                material,
+                transform,
+            } => {
+                if (0..3).any(|axis| min[axis] == max[axis]) {
+                    return Err(invalid(
+                        format!("{}.max", prefix),
+                        "must differ from min along every axis",
+                    ));
+                }
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                let cuboid = Cuboid::new(vector(*min), vector(*max), mat);
+                if transform.is_empty() {
+                    add(world, cuboid, self.is_light(material));
+                } else {
+                    let transform = to_transform(transform, &format!("{}.transform", prefix))?;
+                    let instance = Instance::new(Arc::new(cuboid), transform);
+                    add(world, instance, self.is_light(material));
+                }
+            }
+            ObjectDesc::Triangle { vertices, material } => {
+                let mat = self.material(material, &format!("{}.material", prefix))?;
+                let [a, b, c] = *vertices;
+                let triangle = Triangle::new(vector(a), vector(b), vector(c), mat);
+                add(world, triangle, self.is_light(material));
+            }
+            ObjectDesc::Obj { path, transform } => {
+                let transform = to_transform(transform, &format!("{}.transform", prefix))?;
+                if !self.meshes.contains_key(path.as_path()) {
+                    let obj = obj::load_meshes(self.base_dir.join(path))
+                        .map_err(|e| invalid(format!("{}.path", prefix), e.to_string()))?;
+                    self.warnings.extend(obj.warnings);
+                    let mut list = HitList::new();
+                    for mesh in obj.meshes {
+                        list.add(mesh);
+                    }
+                    let mesh: Arc<dyn Hittable> = Arc::new(Bvh::new(list));
+                    let lights = obj
+                        .lights
+                        .into_iter()
+                        .map(|light| Arc::new(light) as Arc<dyn Hittable>)
+                        .collect();
+                    self.meshes.insert(path, ObjInstances { mesh, lights });
+                }
+                let obj = &self.meshes[path.as_path()];
+                world.add(Instance::new(Arc::clone(&obj.mesh), transform));
+                for light in &obj.lights {
+                    world.add_light(Instance::new(Arc::clone(light), transform));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_light(&self, material: &str) -> bool {
+        matches!(
+            self.scene.materials.get(material),
+            Some(MaterialDesc::DiffuseLight { .. })
+        )
+    }
+}
+
+// Emissive objects are registered as lights so they get sampled directly
+fn add(world: &mut World, obj: impl Hittable + 'static, light: bool) {
+    if light {
+        world.add_light(obj);
+    } else {
+        world.add(obj);
+    }
+}
+
+fn to_transform(steps: &[TransformStep], field: &str) -> Result<Transform, SceneError> {
+    let mut transform = Transform::identity();
+    for step in steps {
+        let next = match step {
+            TransformStep::Translate(v) => Transform::translate(vector(*v)),
+            TransformStep::Scale(v) => {
+                if v.contains(&0.) {
+                    return Err(invalid(field, "scale factors must not be 0"));
+                }
+                Transform::scale(vector(*v))
+            }
+            TransformStep::RotateX(deg) => Transform::rotate_x(*deg),
+            TransformStep::RotateY(deg) => Transform::rotate_y(*deg),
+            TransformStep::RotateZ(deg) => Transform::rotate_z(*deg),
+        };
+        transform = transform.then(&next);
+    }
+    Ok(transform)
+}
+
+fn check_sun(dir: [f64; 3], turbidity: f64, intensity: f64, field: &str) -> Result<(), SceneError> {
+    if vector(dir).length() == 0. {
+        return Err(invalid(
+            format!("environment.{}", field),
+            "must not be zero",
+        ));
+    }
+    if !(1. ..=20.).contains(&turbidity) {
+        return Err(invalid("environment.turbidity", "must be between 1 and 20"));
+    }
+    if intensity < 0. {
+        return Err(invalid("environment.intensity", "must not be negative"));
+    }
+    Ok(())
+}
+
+// Rectangles need some width along each of their sides
+fn check_extent(range: [f64; 2], field: &str) -> Result<(), SceneError> {
+    if !range[0].is_finite() || !range[1].is_finite() {
+        return Err(invalid(field, "must be finite"));
+    }
+    if range[0] == range[1] {
+        return Err(invalid(field, "must not be empty"));
+    }
+    Ok(())
+}
+
+fn vector(v: [f64; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+fn color(c: [f64; 3]) -> RGB<f64> {
+    RGB::new(c[0], c[1], c[2])
+}
@@ -5,7 +5,14 @@ pub mod image;
 use crate::vec3::Vector3;
 
 use rgb::RGB;
+use std::sync::Arc;
 
 pub trait Texture: Sync + Send {
     fn value(&self, u: f64, v: f64, point: Vector3) -> RGB<f64>;
 }
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn value(&self, u: f64, v: f64, point: Vector3) -> RGB<f64> {
+        (**self).value(u, v, point)
+    }
+}
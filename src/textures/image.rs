@@ -3,6 +3,7 @@ use crate::vec3::Vector3;
 
 use image::{DynamicImage, GenericImageView, ImageError};
 use rgb::RGB;
+use std::path::Path;
 
 pub struct ImageTexture {
     img: Result<DynamicImage, ImageError>,
@@ -14,6 +15,12 @@ impl ImageTexture {
 
         Self { img }
     }
+
+    pub fn open(filename: &Path) -> Result<Self, ImageError> {
+        let img = image::open(filename)?;
+
+        Ok(Self { img: Ok(img) })
+    }
 }
 
 impl Texture for ImageTexture {
@@ -26,8 +33,8 @@ impl Texture for ImageTexture {
         let width = img_inner.width() as f64;
         let height = img_inner.height() as f64;
 
-        let u = u.max(0.0).min(1.0);
-        let v = 1.0 - v.max(0.0).min(1.0);
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
 
         let mut i = u * width;
         let mut j = v * height;
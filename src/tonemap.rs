@@ -0,0 +1,117 @@
+use rgb::RGB;
+
+// Maps scene radiance to displayable [0, 1] values before sRGB encoding
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum ToneMap {
+    // Clips everything above 1.0
+    #[default]
+    Clamp,
+    // L / (1 + L) on luminance. Never reaches white.
+    Reinhard,
+    // Reinhard with luminance `white` and above mapped to white
+    ExtendedReinhard {
+        white: f64,
+    },
+    // Krzysztof Narkowicz's fit of the ACES filmic curve
+    Aces,
+    // John Hable's filmic curve from Uncharted 2
+    Uncharted2,
+}
+
+impl ToneMap {
+    pub const NAMES: [&'static str; 5] = [
+        "clamp",
+        "reinhard",
+        "extended_reinhard",
+        "aces",
+        "uncharted2",
+    ];
+
+    // White point of the extended Reinhard operator when none is given
+    pub const DEFAULT_WHITE: f64 = 4.;
+
+    // `white` is only used by the extended Reinhard operator
+    pub fn from_name(name: &str, white: f64) -> Option<ToneMap> {
+        match name {
+            "clamp" => Some(ToneMap::Clamp),
+            "reinhard" => Some(ToneMap::Reinhard),
+            "extended_reinhard" => Some(ToneMap::ExtendedReinhard { white }),
+            "aces" => Some(ToneMap::Aces),
+            "uncharted2" => Some(ToneMap::Uncharted2),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, color: RGB<f64>) -> RGB<f64> {
+        let mapped = match *self {
+            ToneMap::Clamp => color,
+            ToneMap::Reinhard => scale_luminance(color, |l| l / (1. + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1. + l / (white * white)) / (1. + l))
+            }
+            ToneMap::Aces => per_channel(color, |x| {
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+            ToneMap::Uncharted2 => {
+                let white_scale = 1. / uncharted2_curve(11.2);
+                per_channel(color, |x| uncharted2_curve(2. * x) * white_scale)
+            }
+        };
+        per_channel(mapped, |x| x.clamp(0., 1.))
+    }
+}
+
+pub fn luminance(color: RGB<f64>) -> f64 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+fn scale_luminance(color: RGB<f64>, f: impl Fn(f64) -> f64) -> RGB<f64> {
+    let l = luminance(color);
+    if l <= 0. {
+        return RGB::new(0., 0., 0.);
+    }
+    color * (f(l) / l)
+}
+
+fn per_channel(color: RGB<f64>, f: impl Fn(f64) -> f64) -> RGB<f64> {
+    RGB::new(f(color.r), f(color.g), f(color.b))
+}
+
+fn uncharted2_curve(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+// Scales linear radiance by 2^stops
+pub fn expose(color: RGB<f64>, stops: f64) -> RGB<f64> {
+    color * 2f64.powf(stops)
+}
+
+// sRGB transfer function for a linear value in [0, 1]
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+// Inverse of `srgb_encode`
+pub fn srgb_decode(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Blue -> cyan -> green -> yellow -> red for t in [0, 1]
+pub fn heat(t: f64) -> RGB<f64> {
+    let t = t.clamp(0., 1.) * 4.;
+    match t {
+        t if t < 1. => RGB::new(0., t, 1.),
+        t if t < 2. => RGB::new(0., 1., 2. - t),
+        t if t < 3. => RGB::new(t - 2., 1., 0.),
+        t => RGB::new(1., 4. - t, 0.),
+    }
+}
@@ -0,0 +1,221 @@
+use crate::vec3::Vector3;
+
+use std::ops::Mul;
+
+// Row-major 4x4 matrix, applied to column vectors
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+// Affine transform along with its inverse
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Transform {
+    pub matrix: Matrix4,
+    pub inverse: Matrix4,
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { m }
+    }
+
+    pub fn identity() -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Matrix4 { m }
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4 { m }
+    }
+
+    // Gauss-Jordan elimination with partial pivoting. None if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+        Some(Matrix4 { m: inv })
+    }
+
+    pub fn point(&self, p: Vector3) -> Vector3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Vector3::new(x, y, z)
+        } else {
+            Vector3::new(x, y, z) / w
+        }
+    }
+
+    // Ignores the translation
+    pub fn vector(&self, v: Vector3) -> Vector3 {
+        let m = &self.m;
+        Vector3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    // None if the matrix can't be inverted
+    pub fn from_matrix(matrix: Matrix4) -> Option<Transform> {
+        Some(Transform {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn translate(offset: Vector3) -> Transform {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for i in 0..3 {
+            matrix.m[i][3] = offset[i];
+            inverse.m[i][3] = -offset[i];
+        }
+        Transform { matrix, inverse }
+    }
+
+    pub fn scale(factors: Vector3) -> Transform {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for i in 0..3 {
+            matrix.m[i][i] = factors[i];
+            inverse.m[i][i] = 1.0 / factors[i];
+        }
+        Transform { matrix, inverse }
+    }
+
+    // Counterclockwise rotation in degrees around an arbitrary axis
+    pub fn rotate(axis: Vector3, degrees: f64) -> Transform {
+        let a = axis.unit_vec();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        let mut matrix = Matrix4::identity();
+        matrix.m[0][0] = a.x * a.x + (1.0 - a.x * a.x) * cos;
+        matrix.m[0][1] = a.x * a.y * (1.0 - cos) - a.z * sin;
+        matrix.m[0][2] = a.x * a.z * (1.0 - cos) + a.y * sin;
+        matrix.m[1][0] = a.x * a.y * (1.0 - cos) + a.z * sin;
+        matrix.m[1][1] = a.y * a.y + (1.0 - a.y * a.y) * cos;
+        matrix.m[1][2] = a.y * a.z * (1.0 - cos) - a.x * sin;
+        matrix.m[2][0] = a.x * a.z * (1.0 - cos) - a.y * sin;
+        matrix.m[2][1] = a.y * a.z * (1.0 - cos) + a.x * sin;
+        matrix.m[2][2] = a.z * a.z + (1.0 - a.z * a.z) * cos;
+
+        // Rotations are orthogonal
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Transform {
+        Transform::rotate(Vector3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Transform {
+        Transform::rotate(Vector3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Transform {
+        Transform::rotate(Vector3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    // Applies this transform, then `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn inverted(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, p: Vector3) -> Vector3 {
+        self.matrix.point(p)
+    }
+
+    pub fn vector(&self, v: Vector3) -> Vector3 {
+        self.matrix.vector(v)
+    }
+
+    // Normals transform by the inverse transpose to stay perpendicular to the surface.
+    // The result isn't normalized.
+    pub fn normal(&self, n: Vector3) -> Vector3 {
+        self.inverse.transpose().vector(n)
+    }
+
+    // Ratio of areas after and before the transform, for a small patch of surface
+    // with normal `n`
+    pub fn area_scale(&self, n: Vector3) -> f64 {
+        let m = &self.matrix.m;
+        let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        det.abs() * self.normal(n).length() / n.length()
+    }
+}
@@ -1,4 +1,6 @@
-use rand::Rng;
+use crate::samplers::Sampler;
+
+use rand::{Rng, RngCore};
 use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
@@ -14,8 +16,7 @@ impl Vector3 {
         Vector3 { x, y, z }
     }
 
-    pub fn random(min: f64, max: f64) -> Vector3 {
-        let mut rng = rand::thread_rng();
+    pub fn random(min: f64, max: f64, rng: &mut dyn RngCore) -> Vector3 {
         Vector3::new(
             rng.gen_range(min, max),
             rng.gen_range(min, max),
@@ -23,18 +24,12 @@ impl Vector3 {
         )
     }
 
-    pub fn random_in_unit_sphere() -> Vector3 {
-        loop {
-            let p = Vector3::random(-1., 1.);
-            if p.dot(&p) >= 1. {
-                continue;
-            }
-            return p;
-        }
+    pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Vector3 {
+        Vector3::random_unit_vec(sampler) * sampler.get_1d().cbrt()
     }
 
-    pub fn random_in_hemisphere(normal: &Vector3) -> Vector3 {
-        let in_unit_sphere = Vector3::random_in_unit_sphere();
+    pub fn random_in_hemisphere(normal: &Vector3, sampler: &mut dyn Sampler) -> Vector3 {
+        let in_unit_sphere = Vector3::random_in_unit_sphere(sampler);
         if in_unit_sphere.dot(normal) > 0.0 {
             in_unit_sphere
         } else {
@@ -42,14 +37,22 @@ impl Vector3 {
         }
     }
 
-    pub fn random_unit_vec() -> Vector3 {
-        let mut rng = rand::thread_rng();
-        let a = rng.gen_range(0., 2. * std::f64::consts::PI);
-        let z: f64 = rng.gen_range(-1., 1.);
+    pub fn random_unit_vec(sampler: &mut dyn Sampler) -> Vector3 {
+        let (r1, r2) = sampler.get_2d();
+        let a = 2. * std::f64::consts::PI * r1;
+        let z = 1. - 2. * r2;
         let r = (1. - (z * z)).sqrt();
         Vector3::new(r * a.cos(), r * a.sin(), z)
     }
 
+    // Direction in the hemisphere around +z, with density cos(theta) / pi
+    pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vector3 {
+        let (r1, r2) = sampler.get_2d();
+        let phi = 2. * std::f64::consts::PI * r1;
+        let r = r2.sqrt();
+        Vector3::new(phi.cos() * r, phi.sin() * r, (1. - r2).sqrt())
+    }
+
     pub fn length(&self) -> f64 {
         ((self.x * self.x) + (self.y * self.y) + (self.z * self.z)).sqrt()
     }
@@ -78,6 +81,22 @@ impl Vector3 {
         let r = Vector3::new(self.x, self.y, self.z);
         r - (*normal * r.dot(normal) * 2.)
     }
+
+    pub fn min(&self, other: &Vector3) -> Vector3 {
+        Vector3::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(&self, other: &Vector3) -> Vector3 {
+        Vector3::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
 }
 
 impl Add for Vector3 {
@@ -1,115 +1,482 @@
+use crate::adaptive::{Adaptive, PixelStats};
 use crate::camera::Camera;
+use crate::checkpoint::{Checkpoint, CheckpointError, Settings};
+use crate::environment::{Environment, Gradient};
+use crate::film::Film;
+use crate::filters::Filter;
+use crate::integrators::{path::PathTracer, Integrator};
+use crate::objects::bvh::Bvh;
 use crate::objects::{HitList, Hittable};
+use crate::output::{self, HdrFormat, OutputError};
+use crate::progressive::{Pass, PassCallback, Progressive};
 use crate::ray::Ray;
+use crate::samplers::{sobol::SobolSampler, Sampler};
+use crate::tonemap::{self, ToneMap};
 use crate::vec3::Vector3;
 
 use image::{Rgb, RgbImage};
 use rand::Rng;
 use rayon::prelude::*;
 use rgb::RGB;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+// Rows rendered together by one thread
+const STRIP_ROWS: u32 = 4;
 
 pub struct World {
     pub objects: HitList,
+    // Shared with `objects`, see `add_light`
+    pub lights: HitList,
     pub cam: Camera,
     pub width: u32,
     pub height: u32,
     pub output: RgbImage,
+    // Samples in every pixel, or the minimum with adaptive sampling
     pub spp: u32,
+    pub adaptive: Option<Adaptive>,
+    // How samples are weighted into the pixels around them
+    pub filter: Filter,
+    // Render in passes, saving the image after each
+    pub progressive: Option<Progressive>,
+    // Called after every pass of the render
+    pub on_pass: Option<PassCallback>,
+    // Where to save the render's progress so it can be resumed
+    pub checkpoint: Option<String>,
+    // Where to save an image of the number of samples taken in each pixel
+    pub sample_map: Option<String>,
+    // Hard cap on the number of bounces
     pub max_depth: u32,
+    // Paths deeper than this are terminated randomly based on their throughput
+    pub rr_depth: u32,
+    // Seen by rays that leave the scene
+    pub environment: Box<dyn Environment>,
+    pub integrator: Box<dyn Integrator>,
+    // Where each sample's random numbers come from. Every pixel gets its own copy.
+    pub sampler: Box<dyn Sampler>,
+    // The same seed always gives the same image. Unseeded renders pick a random one.
+    pub seed: Option<u64>,
+    // Applied when writing 8-bit images; HDR output is left linear
+    pub tone_map: ToneMap,
+    // In stops
+    pub exposure: f64,
 }
 
-impl World {
-    pub fn new() -> World {
-        let spp = 25;
-        let max_depth = 10;
-
-        let aspect_ratio = 16. / 9.;
-        let width = 426;
-        let height = (width as f64 / aspect_ratio) as u32;
-        let from = Vector3::new(5.0, 1.0, 3.0);
-        let to = Vector3::new(0.0, 2.0, 0.0);
-
-        let cam = Camera::new(
-            from,
-            to,
-            Vector3::new(0.0, 1.0, 0.0),
-            70.0,
-            aspect_ratio,
-            0.1,
-            10.0,
-        );
+pub struct WorldBuilder {
+    width: u32,
+    height: Option<u32>,
+    aspect_ratio: f64,
+    spp: u32,
+    adaptive: Option<Adaptive>,
+    filter: Filter,
+    progressive: Option<Progressive>,
+    max_depth: u32,
+    rr_depth: u32,
+    cam: Option<Camera>,
+    environment: Box<dyn Environment>,
+    integrator: Box<dyn Integrator>,
+    sampler: Box<dyn Sampler>,
+    seed: Option<u64>,
+    tone_map: ToneMap,
+    exposure: f64,
+}
+
+impl WorldBuilder {
+    pub fn new() -> WorldBuilder {
+        WorldBuilder {
+            width: 426,
+            height: None,
+            aspect_ratio: 16. / 9.,
+            spp: 25,
+            adaptive: None,
+            filter: Filter::default(),
+            progressive: None,
+            max_depth: 10,
+            rr_depth: 3,
+            cam: None,
+            environment: Box::new(Gradient::default()),
+            integrator: Box::new(PathTracer),
+            sampler: Box::new(SobolSampler::new()),
+            seed: None,
+            tone_map: ToneMap::Clamp,
+            exposure: 0.,
+        }
+    }
+
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    // Overrides the aspect ratio when set
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn spp(mut self, spp: u32) -> Self {
+        self.spp = spp;
+        self
+    }
+
+    // Samples noisy pixels up to `max_spp` times, see `Adaptive`
+    pub fn adaptive(mut self, threshold: f64, max_spp: u32) -> Self {
+        self.adaptive = Some(Adaptive { threshold, max_spp });
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn progressive(mut self, progressive: Progressive) -> Self {
+        self.progressive = Some(progressive);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn rr_depth(mut self, rr_depth: u32) -> Self {
+        self.rr_depth = rr_depth;
+        self
+    }
+
+    // The camera's aspect ratio is adjusted to match the image size
+    pub fn camera(mut self, cam: Camera) -> Self {
+        self.cam = Some(cam);
+        self
+    }
+
+    pub fn environment(mut self, environment: impl Environment + 'static) -> Self {
+        self.environment = Box::new(environment);
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Box<dyn Integrator>) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn sampler(mut self, sampler: Box<dyn Sampler>) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    pub fn exposure(mut self, stops: f64) -> Self {
+        self.exposure = stops;
+        self
+    }
+
+    pub fn build(self) -> World {
+        let width = self.width.max(1);
+        let height = self
+            .height
+            .unwrap_or((width as f64 / self.aspect_ratio) as u32)
+            .max(1);
+        let aspect_ratio = width as f64 / height as f64;
+
+        let cam = match self.cam {
+            Some(mut cam) => {
+                cam.set_aspect_ratio(aspect_ratio);
+                cam
+            }
+            None => Camera::new(
+                Vector3::new(5.0, 1.0, 3.0),
+                Vector3::new(0.0, 2.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                70.0,
+                aspect_ratio,
+                0.1,
+                10.0,
+            ),
+        };
 
         World {
             objects: HitList::new(),
+            lights: HitList::new(),
             cam,
             width,
             height,
             output: RgbImage::new(width, height),
-            spp,
-            max_depth,
+            spp: self.spp.max(1),
+            adaptive: self.adaptive,
+            filter: self.filter,
+            progressive: self.progressive,
+            on_pass: None,
+            checkpoint: None,
+            sample_map: None,
+            max_depth: self.max_depth,
+            rr_depth: self.rr_depth,
+            environment: self.environment,
+            integrator: self.integrator,
+            sampler: self.sampler,
+            seed: self.seed,
+            tone_map: self.tone_map,
+            exposure: self.exposure,
         }
     }
+}
+
+impl Default for WorldBuilder {
+    fn default() -> Self {
+        WorldBuilder::new()
+    }
+}
+
+impl World {
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder::new()
+    }
+
+    // Renders the scene to `filename`. .exr, .hdr and .pfm files get the unclamped
+    // radiance; any other extension is saved as an 8-bit image.
+    pub fn run(&mut self, filename: &str) -> Result<(), OutputError> {
+        self.render(filename, None)
+    }
+
+    // Continues the render saved in `checkpoint`. The result is the same as if it
+    // had never stopped, as long as the scene hasn't changed.
+    pub fn resume(&mut self, filename: &str, checkpoint: Checkpoint) -> Result<(), OutputError> {
+        checkpoint.settings.check(&self.settings())?;
+        if let Some(seed) = self.seed.filter(|seed| *seed != checkpoint.seed) {
+            return Err(CheckpointError::Mismatch {
+                setting: "seed".to_string(),
+                checkpoint: checkpoint.seed.to_string(),
+                render: seed.to_string(),
+            }
+            .into());
+        }
+        self.render(filename, Some(checkpoint))
+    }
+
+    fn render(&mut self, filename: &str, resume: Option<Checkpoint>) -> Result<(), OutputError> {
+        self.finalize();
+        let start = Instant::now();
+        let settings = self.settings();
+        let (max_spp, pass_spp) = (settings.max_spp, settings.pass_spp);
 
-    pub fn run(&mut self, filename: &str) {
-        let colors: Vec<Vec<RGB<f64>>> = (0..self.height)
-            .into_par_iter()
-            .rev()
-            .map(|row| {
-                (0..self.width)
-                    .into_par_iter()
-                    .map(|col| {
-                        let mut rng = rand::thread_rng();
-                        let mut color = RGB::new(0., 0., 0.);
-
-                        for _ in 0..self.spp {
-                            let u = (col as f64 + rng.gen_range(0., 1.)) / (self.width as f64);
-                            let v = (row as f64 + rng.gen_range(0., 1.)) / (self.height as f64);
-                            let r = self.cam.ray(u, v);
-                            color = color + self.get_color(&r, 0);
-                        }
-                        color
-                    })
-                    .collect()
+        let mut state = match resume {
+            Some(checkpoint) => checkpoint,
+            None => {
+                let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+                Checkpoint::new(settings, seed, self.filter)
+            }
+        };
+        let mut last_snapshot = start;
+        while state.spp_done < max_spp {
+            let end = (state.spp_done + pass_spp).min(max_spp);
+            self.render_pass(&mut state.film, &mut state.stats, state.seed, end);
+            state.spp_done = end;
+
+            let elapsed = start.elapsed();
+            if let Some(on_pass) = &self.on_pass {
+                on_pass(&Pass {
+                    spp: end,
+                    max_spp,
+                    elapsed,
+                });
+            }
+            let progressive = match self.progressive {
+                Some(progressive) => progressive,
+                None => break,
+            };
+            if progressive.time_limit.is_some_and(|limit| elapsed >= limit) {
+                break;
+            }
+            let snapshot_due = progressive
+                .snapshot_interval
+                .is_none_or(|interval| last_snapshot.elapsed() >= interval);
+            if end < max_spp && snapshot_due {
+                self.save(&state, filename)?;
+                last_snapshot = Instant::now();
+            }
+        }
+
+        if let Some(path) = &self.sample_map {
+            let counts: Vec<u32> = state.film.pixels().map(|px| px.samples).collect();
+            self.write_sample_map(path, &counts, max_spp)?;
+        }
+        self.save(&state, filename)
+    }
+
+    // Everything a checkpoint needs to match to be resumed by this world
+    pub fn settings(&self) -> Settings {
+        let max_spp = match self.adaptive {
+            Some(adaptive) => adaptive.max_spp.max(self.spp),
+            None => self.spp,
+        };
+        let pass_spp = match self.progressive {
+            Some(progressive) => progressive.pass_spp.max(1),
+            None => max_spp,
+        };
+        Settings {
+            width: self.width,
+            height: self.height,
+            spp: self.spp,
+            max_spp,
+            adaptive_threshold: self.adaptive.map(|adaptive| adaptive.threshold),
+            pass_spp,
+            filter: self.filter.name().to_string(),
+            filter_radius: self.filter.radius(),
+            sampler: self.sampler.name().to_string(),
+            integrator: self.integrator.name().to_string(),
+            max_depth: self.max_depth,
+            rr_depth: self.rr_depth,
+        }
+    }
+
+    // Brings every pixel up to `end` samples, or fewer if adaptive sampling lets it
+    // stop. Rows are rendered in strips, each into its own film since samples can
+    // land in neighboring pixels. Merging in order keeps renders repeatable.
+    fn render_pass(&self, film: &mut Film, stats: &mut [PixelStats], seed: u64, end: u32) {
+        let strip_len = (STRIP_ROWS * self.width) as usize;
+        let strips: Vec<Film> = stats
+            .par_chunks_mut(strip_len)
+            .enumerate()
+            .map(|(i, stats)| {
+                let first = i as u32 * STRIP_ROWS;
+                let rows = STRIP_ROWS.min(self.height - first);
+                let mut strip = film.strip(first, rows);
+                let mut sampler = self.sampler.clone_box();
+                for (j, stats) in stats.iter_mut().enumerate() {
+                    let pixel = (j as u32 % self.width, first + j as u32 / self.width);
+                    self.render_pixel(&mut strip, sampler.as_mut(), seed, pixel, stats, end);
+                }
+                strip
             })
             .collect();
+        for strip in &strips {
+            film.merge(strip);
+        }
+    }
 
-        for (row, vecrow) in colors.iter().enumerate() {
-            for (col, color) in vecrow.iter().enumerate() {
-                self.write_color(*color, col as u32, row as u32);
+    // Takes the samples for one pixel from where `stats` left off, adding them to `film`
+    fn render_pixel(
+        &self,
+        film: &mut Film,
+        sampler: &mut dyn Sampler,
+        seed: u64,
+        (col, row): (u32, u32),
+        stats: &mut PixelStats,
+        end: u32,
+    ) {
+        for sample in stats.count()..end {
+            if sample >= self.spp && sample % self.spp == 0 && self.converged(stats) {
+                break;
             }
+            sampler.start_sample(seed, (col, row), sample, self.spp);
+            let (du, dv) = sampler.get_2d();
+            let (x, y) = (col as f64 + du, row as f64 + dv);
+            let r = self
+                .cam
+                .ray(x / self.width as f64, y / self.height as f64, sampler);
+            let color = self.get_color(&r, sampler);
+            stats.add(tonemap::luminance(color));
+            film.add_sample((x, y), color);
         }
-        self.output.save(filename).unwrap();
     }
 
-    pub fn get_color(&self, ray: &Ray, depth: u32) -> RGB<f64> {
-        if depth >= self.max_depth {
-            return RGB::new(0., 0., 0.);
+    // Writes the image so far, and the checkpoint if there is one. Goes through a
+    // temporary file so an interrupted write doesn't destroy the last snapshot.
+    fn save(&mut self, state: &Checkpoint, filename: &str) -> Result<(), OutputError> {
+        if let Some(path) = &self.checkpoint {
+            state.save(path)?;
         }
 
-        if let Some(hr) = self.objects.intersect(&ray, 0.001) {
-            if let Some(mr) = hr.mat.scatter(&ray, &hr) {
-                let tmp = self.get_color(&mr.scattered, depth + 1);
-                RGB::new(
-                    tmp.r * mr.attenuation.r,
-                    tmp.g * mr.attenuation.g,
-                    tmp.b * mr.attenuation.b,
-                )
-            } else {
-                RGB::new(0., 0., 0.)
-            }
+        let film = &state.film;
+        let path = Path::new(filename);
+        let name = path
+            .file_name()
+            .map_or_else(Default::default, |n| n.to_string_lossy());
+        let tmp = path.with_file_name(format!(".{}", name));
+        let tmp = tmp.to_string_lossy();
+
+        if let Some(format) = HdrFormat::from_path(filename) {
+            let pixels: Vec<RGB<f64>> = film.pixels().map(|px| px.value()).collect();
+            output::write_hdr(&*tmp, format, self.width, self.height, &pixels)?;
         } else {
-            let unit = ray.dir.unit_vec();
-            let t = 0.5 * (unit.y + 1.);
-            RGB::new(1., 1., 1.) * (1. - t) + RGB::new(0.5, 0.75, 1.) * t
+            self.output = RgbImage::new(self.width, self.height);
+            for (i, px) in film.pixels().enumerate() {
+                let (col, row) = (i as u32 % self.width, i as u32 / self.width);
+                self.write_color(px.value(), col, row);
+            }
+            self.output.save(&*tmp)?;
+        }
+        std::fs::rename(&*tmp, path)?;
+        Ok(())
+    }
+
+    fn converged(&self, stats: &PixelStats) -> bool {
+        match self.adaptive {
+            Some(adaptive) => stats.relative_error() < adaptive.threshold,
+            None => true,
         }
     }
 
+    // HDR formats get the raw counts. Other images show them as a heat map from
+    // blue at `spp` to red at `max_spp`. Renders stopped early stay at blue.
+    fn write_sample_map(
+        &self,
+        path: &str,
+        counts: &[u32],
+        max_spp: u32,
+    ) -> Result<(), OutputError> {
+        if let Some(format) = HdrFormat::from_path(path) {
+            let pixels: Vec<RGB<f64>> = counts
+                .iter()
+                .map(|n| RGB::new(*n as f64, *n as f64, *n as f64))
+                .collect();
+            return output::write_hdr(path, format, self.width, self.height, &pixels);
+        }
+
+        let range = (max_spp - self.spp).max(1) as f64;
+        let mut image = RgbImage::new(self.width, self.height);
+        for (i, n) in counts.iter().enumerate() {
+            let color = tonemap::heat(n.saturating_sub(self.spp) as f64 / range);
+            let encode = |c: f64| (clamp(tonemap::srgb_encode(c), 0., 0.999) * 256.) as u8;
+            let (col, row) = (i as u32 % self.width, i as u32 / self.width);
+            image.put_pixel(
+                col,
+                row,
+                Rgb([encode(color.r), encode(color.g), encode(color.b)]),
+            );
+        }
+        image.save(path)?;
+        Ok(())
+    }
+
+    // Radiance arriving along `ray`, estimated by the selected integrator
+    pub fn get_color(&self, ray: &Ray, sampler: &mut dyn Sampler) -> RGB<f64> {
+        self.integrator.radiance(self, ray, sampler)
+    }
+
+    // `color` is the pixel's average radiance
     fn write_color(&mut self, color: RGB<f64>, col: u32, row: u32) {
-        let scale = 1. / (self.spp as f64);
+        let mapped = self.tone_map.apply(tonemap::expose(color, self.exposure));
         let scaled = RGB::new(
-            (color.r * scale).sqrt(),
-            (color.g * scale).sqrt(),
-            (color.b * scale).sqrt(),
+            tonemap::srgb_encode(mapped.r),
+            tonemap::srgb_encode(mapped.g),
+            tonemap::srgb_encode(mapped.b),
         );
 
         self.output.put_pixel(
@@ -123,9 +490,31 @@ impl World {
         );
     }
 
+    // Changes the output size, adjusting the camera to the new aspect ratio
+    pub fn set_resolution(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.cam.set_aspect_ratio(width as f64 / height as f64);
+    }
+
     pub fn add(&mut self, obj: impl Hittable + 'static) {
         self.objects.add(obj);
     }
+
+    // Adds an emissive object that is also sampled directly when shading other surfaces
+    pub fn add_light(&mut self, obj: impl Hittable + 'static) {
+        let obj = Arc::new(obj);
+        self.lights.add(Arc::clone(&obj));
+        self.objects.add(obj);
+    }
+
+    // Moves every object added so far into a single BVH
+    pub fn finalize(&mut self) {
+        if self.objects.objects.len() > 1 {
+            let objects = std::mem::take(&mut self.objects);
+            self.objects.add(Bvh::new(objects));
+        }
+    }
 }
 
 fn clamp(num: f64, min: f64, max: f64) -> f64 {
@@ -137,3 +526,108 @@ fn clamp(num: f64, min: f64, max: f64) -> f64 {
     }
     num
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::FilmPixel;
+    use crate::materials::{lambertian::Lambertian, metal::Metal};
+    use crate::objects::sphere::Sphere;
+    use crate::textures::color::SolidColor;
+
+    use std::path::PathBuf;
+
+    fn tiny_world() -> World {
+        let mut world = World::builder().width(16).height(8).spp(4).seed(7).build();
+        world.add(Sphere::new(
+            Vector3::new(0., -1000., 0.),
+            1000.,
+            Lambertian::new(SolidColor::new(RGB::new(0.5, 0.5, 0.5))),
+        ));
+        world.add(Sphere::new(
+            Vector3::new(0., 2., 0.),
+            2.,
+            Metal::new(RGB::new(0.7, 0.6, 0.5), 0.2),
+        ));
+        world
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name))
+    }
+
+    // Renders `world` and returns its accumulated pixels, read back from the checkpoint
+    fn render_film(world: &mut World, name: &str) -> Vec<FilmPixel> {
+        let image = temp_path(&format!("{}.pfm", name));
+        let checkpoint = temp_path(&format!("{}.ckpt", name));
+        world.checkpoint = Some(checkpoint.to_string_lossy().into_owned());
+        world.run(&image.to_string_lossy()).unwrap();
+        let pixels = Checkpoint::load(&checkpoint).unwrap().film.pixels;
+        std::fs::remove_file(image).unwrap();
+        std::fs::remove_file(checkpoint).unwrap();
+        pixels
+    }
+
+    #[test]
+    fn sample_map_of_render_stopped_early() {
+        let mut world = tiny_world();
+        world.progressive = Some(Progressive {
+            time_limit: Some(std::time::Duration::ZERO),
+            ..Progressive::default()
+        });
+        let map = temp_path("early-map.png");
+        world.sample_map = Some(map.to_string_lossy().into_owned());
+
+        let pixels = render_film(&mut world, "early");
+        assert!(pixels.iter().all(|px| px.samples < world.spp));
+        assert!(map.exists());
+        std::fs::remove_file(map).unwrap();
+    }
+
+    #[test]
+    fn resumed_render_matches_uninterrupted_one() {
+        let progressive_world = |time_limit| {
+            let mut world = tiny_world();
+            world.filter = Filter::Mitchell { radius: 2. };
+            world.progressive = Some(Progressive {
+                time_limit,
+                ..Progressive::default()
+            });
+            world
+        };
+        let straight = render_film(&mut progressive_world(None), "straight");
+
+        // Stops after the first of four passes
+        let image = temp_path("resumed.pfm");
+        let checkpoint = temp_path("resumed.ckpt");
+        let mut world = progressive_world(Some(std::time::Duration::ZERO));
+        world.checkpoint = Some(checkpoint.to_string_lossy().into_owned());
+        world.run(&image.to_string_lossy()).unwrap();
+        let saved = Checkpoint::load(&checkpoint).unwrap();
+        assert_eq!(saved.spp_done, 1);
+
+        let mut world = progressive_world(None);
+        world.checkpoint = Some(checkpoint.to_string_lossy().into_owned());
+        world.resume(&image.to_string_lossy(), saved).unwrap();
+        let resumed = Checkpoint::load(&checkpoint).unwrap();
+        std::fs::remove_file(image).unwrap();
+        std::fs::remove_file(checkpoint).unwrap();
+
+        assert_eq!(resumed.spp_done, 4);
+        assert_eq!(resumed.film.pixels, straight);
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_with_any_thread_count() {
+        let render = |threads: usize, name: &str| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| render_film(&mut tiny_world(), name))
+        };
+        let single = render(1, "threads1");
+        assert!(single.iter().any(|px| px.color.g > 0.));
+        assert_eq!(single, render(4, "threads4"));
+    }
+}